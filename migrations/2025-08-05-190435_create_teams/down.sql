@@ -0,0 +1,4 @@
+-- Dropping the tables also drops their foreign keys, including the ones to users
+DROP TABLE IF EXISTS "team_members";
+DROP TABLE IF EXISTS "invitations";
+DROP TABLE IF EXISTS "teams";
@@ -0,0 +1,8 @@
+DROP POLICY IF EXISTS "team_members_team_isolation" ON "team_members";
+ALTER TABLE "team_members" DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "invitations_team_isolation" ON "invitations";
+ALTER TABLE "invitations" DISABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS "activity_logs_team_isolation" ON "activity_logs";
+ALTER TABLE "activity_logs" DISABLE ROW LEVEL SECURITY;
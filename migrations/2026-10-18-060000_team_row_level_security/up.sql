@@ -0,0 +1,15 @@
+-- Team isolation policies for team-scoped tables: rows of the team in app.current_team_id,
+-- and none when it isn't set. They only apply once row-level security is enabled on the
+-- tables, which migrate does when DB_ROW_LEVEL_SECURITY=true. Even then the role owning
+-- the tables, which runs the migrations, skips them, as do superusers and BYPASSRLS roles.
+CREATE POLICY "activity_logs_team_isolation" ON "activity_logs"
+	USING ("team_id" = NULLIF(current_setting('app.current_team_id', true), '')::integer)
+	WITH CHECK ("team_id" = NULLIF(current_setting('app.current_team_id', true), '')::integer);
+
+CREATE POLICY "invitations_team_isolation" ON "invitations"
+	USING ("team_id" = NULLIF(current_setting('app.current_team_id', true), '')::integer)
+	WITH CHECK ("team_id" = NULLIF(current_setting('app.current_team_id', true), '')::integer);
+
+CREATE POLICY "team_members_team_isolation" ON "team_members"
+	USING ("team_id" = NULLIF(current_setting('app.current_team_id', true), '')::integer)
+	WITH CHECK ("team_id" = NULLIF(current_setting('app.current_team_id', true), '')::integer);
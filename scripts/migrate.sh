@@ -1,3 +1,4 @@
 #!/bin/bash
 
-cargo run --bin migrate --features setup-deps
\ No newline at end of file
+# Usage: scripts/migrate.sh [run | rollback [--steps N | --to VERSION] | redo | status | plan [--dry-run]]
+cargo run --bin migrate -- "$@"
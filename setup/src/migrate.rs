@@ -1,24 +1,34 @@
 use crate::SetupError;
+use brize::db::Db;
+use brize::tools::PoolSettings;
 use colored::*;
-use std::process::Command;
 
 pub fn run(database_url: &str) -> Result<(), SetupError> {
     println!("{}\n", "   Running Migrations   ".on_white().black().bold());
 
-    let output = Command::new("diesel")
-        .args(&["migration", "run", "--migration-dir", "setup/migrations"])
-        .env("DATABASE_URL", database_url)
-        .output()
-        .map_err(|e| SetupError(format!("Failed to execute diesel command: {}", e)))?;
+    let pool = PoolSettings {
+        schema: crate::setup::get_default_schema()?,
+        ..PoolSettings::default()
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| SetupError(format!("Failed to start async runtime: {}", e)))?;
 
-    if output.status.success() {
-        println!("{}", "✅ Migrations completed \n".green());
-        Ok(())
+    let applied = runtime
+        .block_on(async {
+            let db = Db::connect_as_owner(database_url, &pool).await?;
+            db.migrate().await
+        })
+        .map_err(|e| SetupError(format!("Migration failed: {}", e)))?;
+
+    for migration in &applied {
+        println!("{}", format!("✅ Applied {}", migration.name).green());
+    }
+    if applied.is_empty() {
+        println!("{}", "✅ Database is up to date \n".green());
     } else {
-        let error_msg = String::from_utf8_lossy(&output.stderr);
-        Err(SetupError(format!(
-            "Diesel migration failed: {}",
-            error_msg
-        )))
+        println!("{}", "✅ Migrations completed \n".green());
     }
+    Ok(())
 }
@@ -1,6 +1,7 @@
 use crate::SetupError;
-use crate::schema::{team_members, teams, users};
-use bcrypt::{DEFAULT_COST, hash, verify};
+use bcrypt::{DEFAULT_COST, hash};
+use brize::db::schema::{team_members, teams, users};
+use brize::tools::AppError;
 use colored::*;
 use diesel::prelude::*;
 
@@ -9,9 +10,7 @@ use diesel::prelude::*;
 #[diesel(check_for_backend(diesel::pg::Pg))]
 struct User {
     id: i32,
-    name: Option<String>,
     email: String,
-    role: String,
 }
 
 #[derive(Insertable)]
@@ -45,55 +44,180 @@ struct NewTeamMember {
     role: String,
 }
 
+// Whether a seeded row was inserted by this run or left over from an earlier one
+enum Seeded {
+    Created,
+    Existing,
+}
+
 pub fn run(db_url: &str) -> Result<(), SetupError> {
     println!("{}\n", "   Seeding Database   ".on_white().black().bold());
 
-    let mut conn =
-        PgConnection::establish(db_url).map_err(|_| SetupError("DB Conn failed".to_string()))?;
+    let mut conn = PgConnection::establish(db_url).map_err(|e| {
+        SetupError(format!(
+            "Failed to connect to the database: {}",
+            AppError::from(e)
+        ))
+    })?;
+    let password_hash = hash_password("password")
+        .map_err(|e| SetupError(format!("Failed to hash password: {}", e)))?;
+
+    // All or nothing, so a failed run never leaves a half-seeded database behind
+    let seeded = conn.transaction(|conn| seed(conn, password_hash)).map_err(
+        |e: diesel::result::Error| SetupError(format!("Seeding failed: {}", AppError::from(e))),
+    )?;
+
+    let mut created = 0;
+    for (outcome, row) in &seeded {
+        match outcome {
+            Seeded::Created => {
+                created += 1;
+                println!("{}", format!("✅ Created {}", row).green());
+            }
+            Seeded::Existing => println!("{}", format!("✅ Found existing {}", row).green()),
+        }
+    }
+    println!(
+        "{}",
+        format!(
+            "✅ Seeding completed, {} created and {} existing \n",
+            created,
+            seeded.len() - created
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+// Rows are matched by their natural key: the user's email, the team's name and the
+// (team, user) pair of a membership
+fn seed(conn: &mut PgConnection, password_hash: String) -> QueryResult<Vec<(Seeded, String)>> {
+    let mut seeded = Vec::new();
 
+    // An existing admin keeps its password, which may have been changed since
     let new_user = NewUser {
         name: "admin".to_string(),
         email: "admin@test.com".to_string(),
-        password_hash: hash_password("password").unwrap(),
+        password_hash,
         role: "admin".to_string(),
     };
+    let inserted = diesel::insert_into(users::table)
+        .values(&new_user)
+        .on_conflict(users::email)
+        .do_nothing()
+        .returning(User::as_returning())
+        .get_result(conn)
+        .optional()?;
+    let (outcome, user) = match inserted {
+        Some(user) => (Seeded::Created, user),
+        None => (
+            Seeded::Existing,
+            users::table
+                .filter(users::email.eq(&new_user.email))
+                .select(User::as_select())
+                .get_result(conn)?,
+        ),
+    };
+    seeded.push((outcome, format!("user {}", user.email)));
 
+    // Team names aren't unique, so an existing team is looked up first
     let new_team = NewTeam {
         name: "Test Team".to_string(),
     };
-
-    let user = diesel::insert_into(users::table)
-        .values(&new_user)
-        .returning(User::as_returning())
-        .get_result(&mut conn)
-        .expect("Failed to insert user");
-
-    let team = diesel::insert_into(teams::table)
-        .values(new_team)
-        .returning(Team::as_returning())
-        .get_result(&mut conn)
-        .expect("Failed to insert Team");
+    let existing = teams::table
+        .filter(teams::name.eq(&new_team.name))
+        .order(teams::id)
+        .select(Team::as_select())
+        .first(conn)
+        .optional()?;
+    let (outcome, team) = match existing {
+        Some(team) => (Seeded::Existing, team),
+        None => (
+            Seeded::Created,
+            diesel::insert_into(teams::table)
+                .values(&new_team)
+                .returning(Team::as_returning())
+                .get_result(conn)?,
+        ),
+    };
+    seeded.push((outcome, format!("team {}", team.name)));
 
     let new_member = NewTeamMember {
         user_id: user.id,
         team_id: team.id,
         role: "admin".to_string(),
     };
+    let existing: i64 = team_members::table
+        .filter(team_members::team_id.eq(team.id))
+        .filter(team_members::user_id.eq(user.id))
+        .count()
+        .get_result(conn)?;
+    let outcome = match existing {
+        0 => {
+            diesel::insert_into(team_members::table)
+                .values(&new_member)
+                .execute(conn)?;
+            Seeded::Created
+        }
+        _ => Seeded::Existing,
+    };
+    seeded.push((
+        outcome,
+        format!("membership of {} in {}", user.email, team.name),
+    ));
 
-    diesel::insert_into(team_members::table)
-        .values(&new_member)
-        .execute(&mut conn)
-        .expect("Failed to inster team member");
-
-    println!("{}", "✅ Seeding completed \n".green());
-
-    Ok(())
+    Ok(seeded)
 }
 
 fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
     hash(password, DEFAULT_COST)
 }
 
-fn verify_password(password: &str, hashed: &str) -> Result<bool, bcrypt::BcryptError> {
-    verify(password, hashed)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brize::db::TestDb;
+
+    fn counts(conn: &mut PgConnection) -> [i64; 3] {
+        [
+            users::table.count().get_result(conn).unwrap(),
+            teams::table.count().get_result(conn).unwrap(),
+            team_members::table.count().get_result(conn).unwrap(),
+        ]
+    }
+
+    #[tokio::test]
+    async fn seeding_again_finds_the_rows_instead_of_duplicating_them() {
+        let test_db = TestDb::new().await;
+        let url = format!(
+            "{}?options=-csearch_path%3D{}",
+            test_db.url(),
+            test_db.db().schema()
+        );
+        let mut conn = PgConnection::establish(&url).unwrap();
+
+        let first = seed(&mut conn, "first-hash".to_string()).unwrap();
+        assert!(
+            first
+                .iter()
+                .all(|(outcome, _)| matches!(outcome, Seeded::Created))
+        );
+        assert_eq!(counts(&mut conn), [1, 1, 1]);
+
+        let second = seed(&mut conn, "second-hash".to_string()).unwrap();
+        assert_eq!(second.len(), 3);
+        assert!(
+            second
+                .iter()
+                .all(|(outcome, _)| matches!(outcome, Seeded::Existing))
+        );
+        assert_eq!(counts(&mut conn), [1, 1, 1]);
+
+        let password_hash: String = users::table
+            .select(users::password_hash)
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(password_hash, "first-hash");
+    }
 }
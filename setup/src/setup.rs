@@ -2,13 +2,11 @@ use colored::*;
 use config::Config;
 use dialoguer::Input;
 use std::collections::HashMap;
-<<<<<<<< HEAD:setup/src/main.rs
-use std::default;
-use std::io::{self, Write};
-========
 use std::io::Write;
->>>>>>>> feat/setup:setup/src/setup.rs
 use std::process::Command;
+use url::Url;
+
+use brize::tools::{PoolSettings, is_identifier};
 
 use crate::SetupError;
 
@@ -19,25 +17,27 @@ pub fn run() -> Result<String, SetupError> {
     let stripe_webhook_secret = get_stripe_webhook_secret()?;
 
     println!("{}\n", "  Database Setup  ".on_white().black().bold());
-    let postgres_url = get_db_url()?;
+    let schema = get_default_schema()?;
+    let postgres_url = with_search_path(&get_db_url()?, &schema)?;
+    prepare_db(&postgres_url, &schema)?;
 
     println!("{}\n", "  Application Setup  ".on_white().black().bold());
     let ui_port = get_ui_port()?;
     let auth_secret = generate_auth_secret();
 
     let env_file = format!(
-        r#"DATABASE_URL={}
+        r#"POSTGRES_URL={}
 STRIPE_SECRET_KEY={}
 STRIPE_WEBHOOK_SECRET={}
 BASE_URL=http://localhost:{}
 AUTH_SECRET={}
+DB_SCHEMA={}
 "#,
-        postgres_url, stripe_secret_key, stripe_webhook_secret, ui_port, auth_secret
+        postgres_url, stripe_secret_key, stripe_webhook_secret, ui_port, auth_secret, schema
     );
     let _ = std::fs::write(".env", env_file);
     println!("{}\n", "✅ Environment variables are set in .env".green());
 
-    //TODO maybe do not put things on the public schema like users and teams
     Ok(postgres_url)
 }
 
@@ -87,16 +87,88 @@ fn get_db_url() -> Result<String, SetupError> {
         println!("Missing postgres://, please try again");
         return get_db_url();
     }
-    return Ok(remote_url);
+    Ok(remote_url)
 }
 
-fn start_local_db() -> Result<String, SetupError> {
-    let configs: HashMap<String, String> = Config::builder()
+fn get_configs() -> Result<HashMap<String, String>, SetupError> {
+    Config::builder()
         .add_source(config::File::with_name("./setup/config.toml"))
         .build()
         .map_err(|_| SetupError("Failed to get configs from config.toml".to_string()))?
         .try_deserialize()
-        .map_err(|_| SetupError("Failed to deserialize config.toml".to_string()))?;
+        .map_err(|_| SetupError("Failed to deserialize config.toml".to_string()))
+}
+
+// Checked like DB_SCHEMA, since it ends up in SQL and the connection options unquoted
+pub fn get_default_schema() -> Result<String, SetupError> {
+    let schema = get_configs()?
+        .remove("DEFAULT_SCHEMA")
+        .ok_or_else(|| SetupError("Failed to get DEFAULT_SCHEMA".to_string()))?;
+    if !is_identifier(&schema) {
+        return Err(SetupError(format!(
+            "DEFAULT_SCHEMA must be lowercase letters, digits and _, got {}",
+            schema
+        )));
+    }
+    Ok(schema)
+}
+
+// Points diesel and anything else using the url at the app schema instead of public,
+// unless its libpq `options` already set a search_path. The query is edited as text
+// because libpq doesn't decode the + that form encoding writes for spaces
+fn with_search_path(url: &str, schema: &str) -> Result<String, SetupError> {
+    let mut url =
+        Url::parse(url).map_err(|e| SetupError(format!("Invalid Postgres url: {}", e)))?;
+    let options = url
+        .query_pairs()
+        .find(|(key, _)| key == "options")
+        .map(|(_, value)| value.into_owned());
+    if options.as_deref().is_some_and(sets_search_path) {
+        return Ok(url.to_string());
+    }
+
+    let setting = format!("-csearch_path%3D{}", schema);
+    let mut params: Vec<String> = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .map(str::to_string)
+        .collect();
+    match params
+        .iter_mut()
+        .find(|param| param.starts_with("options="))
+    {
+        Some(param) => param.push_str(&format!("%20{}", setting)),
+        None => params.push(format!("options={}", setting)),
+    }
+    url.set_query(Some(&params.join("&")));
+    Ok(url.to_string())
+}
+
+// Options are server switches, e.g. `-c search_path=app`, `-csearch_path=app` or `--search_path=app`
+fn sets_search_path(options: &str) -> bool {
+    let mut words = options.split_whitespace();
+    while let Some(word) = words.next() {
+        let setting = match word {
+            "-c" => words.next().unwrap_or_default(),
+            _ => word
+                .strip_prefix("-c")
+                .or_else(|| word.strip_prefix("--"))
+                .unwrap_or_default(),
+        };
+        if setting
+            .split_once('=')
+            .is_some_and(|(name, _)| name.replace('-', "_") == "search_path")
+        {
+            return true;
+        }
+    }
+    false
+}
+
+fn start_local_db() -> Result<String, SetupError> {
+    let configs = get_configs()?;
 
     let postgres_user = configs
         .get("POSTGRES_USER")
@@ -168,13 +240,42 @@ volumes:
         .arg("-d")
         .output()
         .map_err(|_| SetupError("Failed to start local Postgres instance".to_string()))?;
-    println!("{}", "✅ Started local Postgres container \n".green());
+    println!("{}", "✅ Started local Postgres container".green());
+
     Ok(format!(
-        "postgres://{}:{}@localhost:{}/postgres?currentSchema={}",
-        postgres_user, postgres_password, postgres_port, default_schema
+        "postgres://{}:{}@localhost:{}/postgres",
+        postgres_user, postgres_password, postgres_port
     ))
 }
 
+// Waits for Postgres to accept connections, then creates the app schema
+fn prepare_db(url: &str, schema: &str) -> Result<(), SetupError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| SetupError(format!("Failed to start async runtime: {}", e)))?;
+    let pool = PoolSettings {
+        schema: schema.to_string(),
+        ..PoolSettings::default()
+    };
+    let db = runtime
+        .block_on(brize::db::connect_with_retry(url, &pool))
+        .map_err(|e| SetupError(format!("Postgres never became ready: {}", e)))?;
+    let health = runtime
+        .block_on(db.health())
+        .map_err(|e| SetupError(format!("Postgres never became ready: {}", e)))?;
+    println!(
+        "{}",
+        format!("✅ Postgres is ready ({:?} round trip)", health.latency).green()
+    );
+
+    runtime
+        .block_on(db.create_schema())
+        .map_err(|e| SetupError(format!("Failed to create schema {}: {}", schema, e)))?;
+    println!("{}", format!("✅ Created schema {} \n", schema).green());
+    Ok(())
+}
+
 fn get_stripe_secret_key() -> Result<String, SetupError> {
     let input: String = Input::new()
         .with_prompt("Please enter your Stripe secret key")
@@ -206,7 +307,7 @@ fn get_ui_port() -> Result<String, SetupError> {
 fn generate_auth_secret() -> String {
     let auth_secret = uuid::Uuid::new_v4().to_string();
     println!("{}", "✅ Generated auth secret".green());
-    return auth_secret;
+    auth_secret
 }
 
 fn clear_line() {
@@ -214,3 +315,33 @@ fn clear_line() {
     print!("\r\x1B[2K"); // Clear the entire line
     std::io::stdout().flush().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_the_search_path_unless_options_set_one() {
+        let with = |url| with_search_path(url, "app").unwrap();
+        assert_eq!(
+            with("postgres://u:p@localhost:5432/postgres"),
+            "postgres://u:p@localhost:5432/postgres?options=-csearch_path%3Dapp"
+        );
+        assert_eq!(
+            with("postgres://localhost/postgres?sslmode=disable&options=-cstatement_timeout%3D5s"),
+            "postgres://localhost/postgres?sslmode=disable&options=-cstatement_timeout%3D5s%20-csearch_path%3Dapp"
+        );
+        for set in [
+            "postgres://localhost/postgres?options=-csearch_path%3Dother",
+            "postgres://localhost/postgres?options=-c%20search_path%3Dother",
+            "postgres://localhost/postgres?options=--search-path%3Dother",
+        ] {
+            assert_eq!(with(set), set);
+        }
+        // Only the options say where tables go, not a database that happens to be named so
+        assert_eq!(
+            with("postgres://localhost/search_path"),
+            "postgres://localhost/search_path?options=-csearch_path%3Dapp"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use brize::db::{Db, Migration, MigrationState, Rollback};
+use brize::tools::{Result, Settings};
+
+const USAGE: &str = "Usage: migrate [run]
+       migrate rollback [--steps N | --to VERSION]
+       migrate redo
+       migrate status
+       migrate plan [--dry-run]";
+
+// Usage: cargo run --bin migrate -- rollback --steps 2
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // brize::db logs retries and migration locks through tracing
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        [] | ["run"] => run().await,
+        ["rollback"] => rollback(Rollback::Steps(1)).await,
+        ["rollback", "--steps", steps] => match steps.parse() {
+            Ok(steps) => rollback(Rollback::Steps(steps)).await,
+            Err(_) => usage(),
+        },
+        ["rollback", "--to", version] => rollback(Rollback::To(version.to_string())).await,
+        ["redo"] => redo().await,
+        ["status"] => status().await,
+        ["plan"] => plan(false).await,
+        ["plan", "--dry-run"] => plan(true).await,
+        _ => usage(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn usage() -> Result<()> {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+async fn connect() -> Result<Db> {
+    let settings = Settings::load()?;
+    Db::connect_as_owner(settings.postgres_url.expose(), &settings.pool).await
+}
+
+async fn run() -> Result<()> {
+    let db = connect().await?;
+    db.create_schema().await?;
+    let applied = db.migrate().await?;
+    print_each("Applied", &applied);
+    if applied.is_empty() {
+        println!("Database is up to date");
+    }
+    Ok(())
+}
+
+async fn rollback(target: Rollback) -> Result<()> {
+    let reverted = connect().await?.rollback(target).await?;
+    print_each("Reverted", &reverted);
+    if reverted.is_empty() {
+        println!("Nothing to roll back");
+    }
+    Ok(())
+}
+
+async fn redo() -> Result<()> {
+    let migration = connect().await?.redo().await?;
+    println!("Redid {}", migration.name);
+    Ok(())
+}
+
+async fn status() -> Result<()> {
+    let statuses = connect().await?.migration_status().await?;
+    let width = statuses.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for status in &statuses {
+        println!(
+            "{:width$}  {:8}  {}",
+            status.name,
+            status.state.as_str(),
+            status.applied_at.as_deref().unwrap_or("-"),
+            width = width
+        );
+    }
+
+    // Worth failing a deploy check over
+    if statuses.iter().any(|s| s.state == MigrationState::Changed) {
+        eprintln!("Some applied migrations were changed since, see above");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Prints the pending SQL; --dry-run also runs it in a transaction that's rolled back
+async fn plan(dry_run: bool) -> Result<()> {
+    let db = connect().await?;
+    let pending = db.plan().await?;
+    if pending.is_empty() {
+        println!("Database is up to date");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("BEGIN;\n");
+    }
+    for migration in &pending {
+        println!("-- {}\n{}\n", migration.name, migration.up.trim_end());
+    }
+    if dry_run {
+        println!("ROLLBACK;\n");
+        db.dry_run().await?;
+        println!("Dry run succeeded, nothing was committed");
+    }
+    Ok(())
+}
+
+fn print_each(verb: &str, migrations: &[&Migration]) {
+    for migration in migrations {
+        println!("{} {}", verb, migration.name);
+    }
+}
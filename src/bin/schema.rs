@@ -0,0 +1,39 @@
+use brize::db::Db;
+use brize::tools::{Result, Settings};
+
+// Usage: cargo run --bin schema -- check
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // brize::db logs retries and migration locks through tracing
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    match std::env::args().nth(1).as_deref() {
+        Some("check") => (),
+        _ => {
+            eprintln!("Usage: schema check");
+            std::process::exit(2);
+        }
+    }
+
+    match check().await {
+        Ok(drift) if drift.is_empty() => println!("Database matches src/db/schema.rs"),
+        Ok(drift) => {
+            for line in drift {
+                println!("{}", line);
+            }
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn check() -> Result<Vec<String>> {
+    let settings = Settings::load()?;
+    let db = Db::connect_as_owner(settings.postgres_url.expose(), &settings.pool).await?;
+    db.check_schema().await
+}
@@ -0,0 +1,28 @@
+use brize::tools::Settings;
+
+// Usage: cargo run --bin settings -- explain
+fn main() {
+    match std::env::args().nth(1).as_deref() {
+        Some("explain") => explain(),
+        _ => {
+            eprintln!("Usage: settings explain");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn explain() {
+    match Settings::explain() {
+        Ok(explanation) => print!("{}", explanation),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Still show the table above when the config is broken, then say why
+    if let Err(e) = Settings::load() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,1078 @@
+use std::time::{Duration, Instant};
+
+use sea_orm::{ConnectionTrait, DatabaseTransaction, DbBackend, Statement, TransactionTrait};
+use sha2::{Digest, Sha256};
+
+use super::Db;
+use super::row_level_security::enable_row_level_security;
+use super::schema_check::check_schema_on;
+use crate::tools::{AppError, Detail, Result};
+
+// Where drizzle-kit's migrator records what it ran
+const DRIZZLE_MIGRATIONS: &str = r#""drizzle"."__drizzle_migrations""#;
+
+// A Drizzle migration history brize can adopt: the files it ran, oldest first, by tag and
+// the SHA-256 drizzle-kit records for each, and the brize migrations they add up to
+struct DrizzleHistory {
+    migrations: &'static [(&'static str, &'static str)],
+    versions: &'static [&'static str],
+}
+
+static DRIZZLE_HISTORIES: &[DrizzleHistory] = &[
+    // The SQL brize shipped before moving to diesel's layout, generated from the SaaS
+    // starter's schema. Its `users` table came with that schema, both files reference it
+    DrizzleHistory {
+        migrations: &[
+            (
+                "0000_initialize",
+                "06c81967fac9774b7759788b09ee72bbaa89ceb2e3d67148e09bd7bbd120adc3",
+            ),
+            (
+                "0001_initialize_with_teams",
+                "9a57cef98a9a81b3671260df26afc9ceaa723b7bac3746100688bf435432b936",
+            ),
+        ],
+        versions: &["20250805190210", "20250805190435", "20250805190448"],
+    },
+];
+
+/// One migration from `migrations/`, embedded in the binary.
+#[derive(Debug)]
+pub struct Migration {
+    /// The migration's directory, e.g. `2025-08-05-190210_create_users`
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+macro_rules! migration {
+    ($name:literal) => {
+        Migration {
+            name: $name,
+            up: include_str!(concat!("../../migrations/", $name, "/up.sql")),
+            down: include_str!(concat!("../../migrations/", $name, "/down.sql")),
+        }
+    };
+}
+
+/// Every migration, oldest first. Add new ones at the end; a test fails when one is missing.
+pub static MIGRATIONS: &[Migration] = &[
+    migration!("2025-08-05-190210_create_users"),
+    migration!("2025-08-05-190435_create_teams"),
+    migration!("2025-08-05-190448_create_activity_log"),
+    migration!("2026-10-18-060000_team_row_level_security"),
+];
+
+/// How far [`Db::rollback`] goes back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rollback {
+    /// The last `n` applied migrations
+    Steps(usize),
+    /// Everything applied after this version, which stays applied. Takes the version or
+    /// the full migration name.
+    To(String),
+}
+
+/// Where one migration stands, see [`Db::migration_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but its `up.sql` was edited since
+    Changed,
+    /// Applied, but not part of this build, e.g. from a newer branch
+    Unknown,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Changed => "changed",
+            MigrationState::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: String,
+    pub name: String,
+    pub state: MigrationState,
+    /// `None` while pending
+    pub applied_at: Option<String>,
+}
+
+// A row of the tracking table
+struct Applied {
+    version: String,
+    name: String,
+    checksum: String,
+    applied_at: String,
+}
+
+// Lives in the app schema, next to the tables it tracks
+const CREATE_TRACKING_TABLE: &str = r#"CREATE TABLE IF NOT EXISTS "schema_migrations" (
+	"version" varchar(50) PRIMARY KEY NOT NULL,
+	"name" text NOT NULL,
+	"checksum" char(64) NOT NULL,
+	"applied_at" timestamp DEFAULT now() NOT NULL
+)"#;
+
+impl Migration {
+    /// The timestamp part of the name as digits, the same version diesel records.
+    pub fn version(&self) -> String {
+        let timestamp = self.name.split('_').next().unwrap_or(self.name);
+        timestamp.chars().filter(char::is_ascii_digit).collect()
+    }
+
+    /// Tables `up.sql` creates, used to adopt databases set up before the tracking table.
+    pub fn created_tables(&self) -> Vec<&'static str> {
+        self.up
+            .lines()
+            .filter_map(|line| {
+                let rest = line.trim().strip_prefix("CREATE TABLE ")?;
+                let rest = rest.strip_prefix("IF NOT EXISTS ").unwrap_or(rest);
+                rest.split_whitespace().next()
+            })
+            .map(|name| name.trim_matches('"'))
+            .collect()
+    }
+
+    /// SHA-256 of `up.sql`. Line endings are normalized so a checkout with CRLFs matches.
+    pub fn checksum(&self) -> String {
+        let up = self.up.replace("\r\n", "\n");
+        format!("{:x}", Sha256::digest(up.as_bytes()))
+    }
+}
+
+impl Db {
+    /// Applies every migration that hasn't been applied yet and returns the ones it applied.
+    ///
+    /// Refuses to apply anything when a migration that already ran has since been edited,
+    /// since the database no longer matches what its file says.
+    ///
+    /// Instances starting at once take turns through an advisory lock, so the ones that
+    /// wait find the work done and apply nothing. Everything runs in the transaction that
+    /// holds the lock, so a failure or crash partway leaves the database as it was.
+    pub async fn migrate(&self) -> Result<Vec<&'static Migration>> {
+        let lock = self.lock_migrations().await?;
+        let result = self.migrate_locked(&lock).await;
+        finish(lock, result).await
+    }
+
+    async fn migrate_locked(&self, lock: &DatabaseTransaction) -> Result<Vec<&'static Migration>> {
+        lock.execute_unprepared(CREATE_TRACKING_TABLE).await?;
+        let adopted = self.adopt(lock).await?;
+        for migration in &adopted {
+            tracing::info!("Adopted {}, its tables already exist", migration.name);
+        }
+        // Tables made elsewhere may have been changed since, e.g. by later Drizzle migrations
+        if !adopted.is_empty() {
+            for drift in check_schema_on(lock).await? {
+                tracing::warn!("Adopted tables differ from schema.rs: {}", drift);
+            }
+        }
+        let pending = pending(lock).await?;
+        for migration in &pending {
+            apply(lock, migration)
+                .await
+                .map_err(|e| failed(migration, e))?;
+        }
+        enable_row_level_security(lock, self.pool.row_level_security).await?;
+        Ok(pending)
+    }
+
+    /// The migrations [`Db::migrate`] would apply, oldest first, without touching the database.
+    pub async fn plan(&self) -> Result<Vec<&'static Migration>> {
+        pending(&self.conn).await
+    }
+
+    /// Runs the [`Db::plan`] in one transaction and rolls it back, so the SQL is checked
+    /// against the real database without committing anything.
+    pub async fn dry_run(&self) -> Result<Vec<&'static Migration>> {
+        let pending = self.plan().await?;
+        let txn = self.conn.begin().await?;
+        for migration in &pending {
+            if let Err(e) = txn.execute_unprepared(migration.up).await {
+                txn.rollback().await?;
+                return Err(failed(migration, e.into()));
+            }
+        }
+        txn.rollback().await?;
+        Ok(pending)
+    }
+
+    /// Every embedded migration plus any applied one this build doesn't know, by version.
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        let applied = applied_migrations(&self.conn).await?;
+        let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+            .iter()
+            .map(|migration| {
+                let version = migration.version();
+                let row = applied.iter().find(|a| a.version == version);
+                let state = match row {
+                    None => MigrationState::Pending,
+                    Some(a) if a.checksum != migration.checksum() => MigrationState::Changed,
+                    Some(_) => MigrationState::Applied,
+                };
+                MigrationStatus {
+                    version,
+                    name: migration.name.to_string(),
+                    state,
+                    applied_at: row.map(|a| a.applied_at.clone()),
+                }
+            })
+            .collect();
+
+        for a in applied {
+            if find_migration(&a.version).is_none() {
+                statuses.push(MigrationStatus {
+                    version: a.version,
+                    name: a.name,
+                    state: MigrationState::Unknown,
+                    applied_at: Some(a.applied_at),
+                });
+            }
+        }
+        statuses.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(statuses)
+    }
+
+    /// Reverts applied migrations newest first and returns the ones it reverted. Like
+    /// [`Db::migrate`], everything runs in the transaction holding the lock, so when one
+    /// fails none of them are reverted.
+    pub async fn rollback(&self, target: Rollback) -> Result<Vec<&'static Migration>> {
+        let lock = self.lock_migrations().await?;
+        let result = rollback_locked(&lock, target).await;
+        finish(lock, result).await
+    }
+
+    /// Reverts the last applied migration and applies it again, e.g. after editing it locally.
+    pub async fn redo(&self) -> Result<&'static Migration> {
+        let lock = self.lock_migrations().await?;
+        let result = self.redo_locked(&lock).await;
+        finish(lock, result).await
+    }
+
+    async fn redo_locked(&self, lock: &DatabaseTransaction) -> Result<&'static Migration> {
+        let Some(migration) = rollback_locked(lock, Rollback::Steps(1)).await?.pop() else {
+            return Err(AppError::NotFound("No migration is applied".into()));
+        };
+        apply(lock, migration)
+            .await
+            .map_err(|e| failed(migration, e))?;
+        enable_row_level_security(lock, self.pool.row_level_security).await?;
+        Ok(migration)
+    }
+
+    // Waits up to `migration_lock_timeout` for the advisory lock. It's scoped to the
+    // returned transaction, which holds one pooled connection until rolled back
+    async fn lock_migrations(&self) -> Result<DatabaseTransaction> {
+        let key = lock_key(self.schema());
+        let timeout = self.pool.migration_lock_timeout;
+        let deadline = Instant::now() + timeout;
+        let lock = self.conn.begin().await?;
+        let mut waiting = false;
+
+        loop {
+            let locked = lock
+                .query_one(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    "SELECT pg_try_advisory_xact_lock($1) AS locked",
+                    [key.into()],
+                ))
+                .await?
+                .map(|row| row.try_get::<bool>("", "locked"))
+                .transpose()?;
+            if locked == Some(true) {
+                if waiting {
+                    tracing::info!("Got the migration lock");
+                }
+                return Ok(lock);
+            }
+
+            let holder = lock_holder(&lock, key).await?;
+            if Instant::now() >= deadline {
+                lock.rollback().await?;
+                return Err(AppError::Database(
+                    format!(
+                        "Timed out after {:?} waiting for the migration lock held by {}",
+                        timeout, holder
+                    )
+                    .into(),
+                ));
+            }
+            if !waiting {
+                tracing::info!("Waiting for the migration lock held by {}", holder);
+                waiting = true;
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    // Databases set up before the tracking table existed, by the diesel CLI, the Drizzle
+    // starter or from the old top-level SQL files, already have some of the tables. Those
+    // migrations are recorded as applied instead of running their DDL again
+    async fn adopt(&self, lock: &DatabaseTransaction) -> Result<Vec<&'static Migration>> {
+        if !applied_migrations(lock).await?.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let drizzle = table_exists(lock, DRIZZLE_MIGRATIONS).await?;
+        if self.schema() != "public"
+            && !table_exists(lock, "users").await?
+            && table_exists(lock, "public.users").await?
+        {
+            let origin = match drizzle {
+                true => "the Drizzle starter",
+                false => "an earlier setup",
+            };
+            return Err(AppError::Config(
+                format!(
+                    "Found tables from {} in the public schema. Set DB_SCHEMA=public to adopt them, or move them into {}",
+                    origin,
+                    self.schema()
+                )
+                .into(),
+            ));
+        }
+
+        // Versions known to be applied, or None to go by which tables exist
+        let versions: Option<Vec<String>> =
+            match table_exists(lock, "__diesel_schema_migrations").await? {
+                true => Some(
+                    column(
+                        lock,
+                        r#"SELECT "version" FROM "__diesel_schema_migrations""#,
+                    )
+                    .await?,
+                ),
+                false if drizzle => drizzle_versions(lock).await?,
+                false => None,
+            };
+
+        // Without a Drizzle history brize knows, only the tables say what ran
+        let verify = drizzle && versions.is_none();
+
+        let mut adopted = Vec::new();
+        for migration in MIGRATIONS {
+            let in_place = match &versions {
+                Some(versions) => versions.contains(&migration.version()),
+                None => {
+                    let tables = migration.created_tables();
+                    let mut all_exist = !tables.is_empty();
+                    for table in tables {
+                        all_exist = all_exist && table_exists(lock, table).await?;
+                    }
+                    all_exist
+                }
+            };
+            // Only a prefix can be adopted, later migrations may build on earlier ones
+            if !in_place {
+                break;
+            }
+            lock.execute(record(migration)).await?;
+            adopted.push(migration);
+        }
+        if verify && !adopted.is_empty() {
+            check_adopted_drizzle_tables(lock, &adopted).await?;
+        }
+        Ok(adopted)
+    }
+}
+
+// Columns later Drizzle migrations added are only warned about, but adopting tables
+// that lack something schema.rs has would leave them broken for good
+async fn check_adopted_drizzle_tables(
+    lock: &DatabaseTransaction,
+    adopted: &[&'static Migration],
+) -> Result<()> {
+    let tables: Vec<&str> = adopted.iter().flat_map(|m| m.created_tables()).collect();
+    let differs: Vec<String> = check_schema_on(lock)
+        .await?
+        .into_iter()
+        .filter(|drift| {
+            let table = drift.split(['.', ':']).next().unwrap_or_default();
+            tables.contains(&table)
+                && !drift.ends_with("is not in schema.rs")
+                && !drift.ends_with("is not in the entities")
+        })
+        .collect();
+    if differs.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::Database(
+        format!(
+            "Drizzle's migration history isn't one brize knows, and its tables differ from schema.rs, so nothing was adopted: {}",
+            differs.join("; ")
+        )
+        .into(),
+    ))
+}
+
+async fn rollback_locked(
+    lock: &DatabaseTransaction,
+    target: Rollback,
+) -> Result<Vec<&'static Migration>> {
+    let applied = applied_migrations(lock).await?;
+
+    let count = match &target {
+        Rollback::Steps(steps) => (*steps).min(applied.len()),
+        Rollback::To(version) => {
+            let Some(position) = applied
+                .iter()
+                .position(|a| a.version == *version || a.name == *version)
+            else {
+                return Err(AppError::NotFound(
+                    format!("Migration {} is not applied", version).into(),
+                ));
+            };
+            applied.len() - position - 1
+        }
+    };
+
+    let mut reverted = Vec::new();
+    for Applied { version, .. } in applied.iter().rev().take(count) {
+        let migration = find_migration(version).ok_or_else(|| {
+            AppError::NotFound(
+                format!(
+                    "Migration {} is applied but not known to this build",
+                    version
+                )
+                .into(),
+            )
+        })?;
+        revert(lock, migration).await.map_err(|e| {
+            AppError::Database(Detail::with_source(
+                format!("Reverting {} failed: {}", migration.name, e),
+                e,
+            ))
+        })?;
+        reverted.push(migration);
+    }
+    Ok(reverted)
+}
+
+async fn revert(lock: &DatabaseTransaction, migration: &Migration) -> Result<()> {
+    lock.execute_unprepared(migration.down).await?;
+    lock.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"DELETE FROM "schema_migrations" WHERE "version" = $1"#,
+        [migration.version().into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+// Commits the lock's transaction when `result` is Ok, otherwise undoes everything in it
+async fn finish<T>(lock: DatabaseTransaction, result: Result<T>) -> Result<T> {
+    match result {
+        Ok(value) => {
+            lock.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            lock.rollback().await?;
+            Err(e)
+        }
+    }
+}
+
+// Who holds the lock, from pg_stat_activity. Connections name their instance, see
+// `instance_name`
+async fn lock_holder(lock: &DatabaseTransaction, key: i64) -> Result<String> {
+    let row = lock
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT a.pid, a.application_name,
+                coalesce(host(a.client_addr), 'local socket') AS client,
+                to_char(a.xact_start, 'YYYY-MM-DD HH24:MI:SS') AS since
+            FROM pg_locks l JOIN pg_stat_activity a ON a.pid = l.pid
+            WHERE l.locktype = 'advisory' AND l.granted AND l.objsubid = 1
+                AND l.database = (SELECT oid FROM pg_database WHERE datname = current_database())
+                AND ((l.classid::bigint << 32) | l.objid::bigint) = $1"#,
+            [key.into()],
+        ))
+        .await?;
+
+    let Some(row) = row else {
+        return Ok("an instance that has just released it".to_string());
+    };
+    let pid: i32 = row.try_get("", "pid")?;
+    let name: String = row.try_get("", "application_name")?;
+    let client: String = row.try_get("", "client")?;
+    let since: Option<String> = row.try_get("", "since")?;
+    Ok(format!(
+        "{} (pid {}, {}, since {})",
+        if name.is_empty() {
+            "unnamed client"
+        } else {
+            &name
+        },
+        pid,
+        client,
+        since.as_deref().unwrap_or("unknown")
+    ))
+}
+
+// Pending migrations, oldest first
+async fn pending<C: ConnectionTrait>(conn: &C) -> Result<Vec<&'static Migration>> {
+    let applied = applied_migrations(conn).await?;
+    let mut pending = Vec::new();
+    for migration in MIGRATIONS {
+        match applied.iter().find(|a| a.version == migration.version()) {
+            None => pending.push(migration),
+            Some(a) if a.checksum != migration.checksum() => {
+                return Err(AppError::Database(Detail::new(format!(
+                    "Migration {} was changed after it was applied. Restore it and add a new migration instead",
+                    migration.name
+                ))));
+            }
+            Some(_) => (),
+        }
+    }
+    Ok(pending)
+}
+
+// Rows of the tracking table, oldest first. Empty before the first migration
+async fn applied_migrations<C: ConnectionTrait>(conn: &C) -> Result<Vec<Applied>> {
+    if !table_exists(conn, "schema_migrations").await? {
+        return Ok(Vec::new());
+    }
+
+    let rows = conn
+        .query_all(Statement::from_string(
+            DbBackend::Postgres,
+            r#"SELECT "version", "name", "checksum",
+                to_char("applied_at", 'YYYY-MM-DD HH24:MI:SS') AS "applied_at"
+            FROM "schema_migrations" ORDER BY "version""#,
+        ))
+        .await?;
+    let mut applied = Vec::new();
+    for row in rows {
+        applied.push(Applied {
+            version: row.try_get("", "version")?,
+            name: row.try_get("", "name")?,
+            checksum: row.try_get("", "checksum")?,
+            applied_at: row.try_get("", "applied_at")?,
+        });
+    }
+    Ok(applied)
+}
+
+// In a transaction of its own, or a savepoint when `conn` is already one
+async fn apply<C: TransactionTrait>(conn: &C, migration: &Migration) -> Result<()> {
+    let txn = conn.begin().await?;
+    txn.execute_unprepared(migration.up).await?;
+    txn.execute(record(migration)).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+// Maps Drizzle's history onto brize versions. `None` for one it doesn't know exactly, such
+// as the starter's own, which is then adopted by its tables and checked against schema.rs
+async fn drizzle_versions(lock: &DatabaseTransaction) -> Result<Option<Vec<String>>> {
+    let hashes = column(
+        lock,
+        r#"SELECT "hash" FROM "drizzle"."__drizzle_migrations" ORDER BY "created_at", "id""#,
+    )
+    .await?;
+    if hashes.is_empty() {
+        return Ok(None);
+    }
+
+    let history = DRIZZLE_HISTORIES.iter().find(|history| {
+        history.migrations.len() == hashes.len()
+            && history
+                .migrations
+                .iter()
+                .zip(&hashes)
+                .all(|((_, known), hash)| known == hash)
+    });
+    let Some(history) = history else {
+        tracing::info!(
+            "Drizzle's migration history ({} entries) isn't one brize knows, adopting by the tables that exist",
+            hashes.len()
+        );
+        return Ok(None);
+    };
+
+    // The history says the tables are there, a missing one means they were changed since
+    for migration in history.versions.iter().filter_map(|v| find_migration(v)) {
+        for table in migration.created_tables() {
+            if !table_exists(lock, table).await? {
+                return Err(AppError::Database(
+                    format!(
+                        "Drizzle recorded the migrations that create {}, but it doesn't exist, so nothing was adopted",
+                        table
+                    )
+                    .into(),
+                ));
+            }
+        }
+    }
+    Ok(Some(
+        history.versions.iter().map(|v| v.to_string()).collect(),
+    ))
+}
+
+async fn column<C: ConnectionTrait>(conn: &C, sql: &str) -> Result<Vec<String>> {
+    let rows = conn
+        .query_all(Statement::from_string(DbBackend::Postgres, sql))
+        .await?;
+    let mut values = Vec::new();
+    for row in rows {
+        values.push(row.try_get_by_index(0)?);
+    }
+    Ok(values)
+}
+
+// Resolved through the search_path unless `name` has a schema
+async fn table_exists<C: ConnectionTrait>(conn: &C, name: &str) -> Result<bool> {
+    let exists = conn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT to_regclass($1) IS NOT NULL AS exists",
+            [name.into()],
+        ))
+        .await?
+        .map(|row| row.try_get::<bool>("", "exists"))
+        .transpose()?;
+    Ok(exists == Some(true))
+}
+
+fn record(migration: &Migration) -> Statement {
+    Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"INSERT INTO "schema_migrations" ("version", "name", "checksum") VALUES ($1, $2, $3)"#,
+        [
+            migration.version().into(),
+            migration.name.into(),
+            migration.checksum().into(),
+        ],
+    )
+}
+
+// One lock per schema, so test databases and other apps sharing the server don't wait on us
+fn lock_key(schema: &str) -> i64 {
+    let digest = Sha256::digest(format!("brize migrations {}", schema).as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    i64::from_be_bytes(bytes) & i64::MAX
+}
+
+fn failed(migration: &Migration, e: AppError) -> AppError {
+    AppError::Database(Detail::with_source(
+        format!("Migration {} failed: {}", migration.name, e),
+        e,
+    ))
+}
+
+fn find_migration(version: &str) -> Option<&'static Migration> {
+    MIGRATIONS.iter().find(|m| m.version() == version)
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    use super::*;
+    use crate::db::TestDb;
+    use crate::entities::users;
+    use crate::tools::PoolSettings;
+
+    #[test]
+    fn embeds_every_migration() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/migrations");
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+
+        let embedded: Vec<&str> = MIGRATIONS.iter().map(|m| m.name).collect();
+        assert_eq!(names, embedded);
+        assert_eq!(MIGRATIONS[0].version(), "20250805190210");
+    }
+
+    // Tables, constraints and policies in the test database's schema
+    async fn snapshot(test_db: &TestDb) -> Vec<String> {
+        let rows = test_db
+            .conn()
+            .query_all(Statement::from_string(
+                DbBackend::Postgres,
+                "SELECT 'table ' || table_name AS item FROM information_schema.tables \
+                 WHERE table_schema = current_schema() AND table_name <> 'schema_migrations' \
+                 UNION ALL SELECT 'constraint ' || constraint_name FROM information_schema.table_constraints \
+                 WHERE constraint_schema = current_schema() AND table_name <> 'schema_migrations' \
+                 AND constraint_type <> 'CHECK' \
+                 UNION ALL SELECT 'policy ' || policyname FROM pg_policies WHERE schemaname = current_schema() \
+                 ORDER BY item",
+            ))
+            .await
+            .unwrap();
+        rows.iter()
+            .map(|row| row.try_get("", "item").unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn applies_and_reverts_every_migration_in_order() {
+        let test_db = TestDb::new().await;
+        let db = test_db.db();
+        let reverted = db
+            .rollback(Rollback::Steps(MIGRATIONS.len()))
+            .await
+            .unwrap();
+        assert_eq!(reverted.len(), MIGRATIONS.len());
+        assert!(snapshot(&test_db).await.is_empty());
+
+        let mut snapshots = vec![Vec::new()];
+        for migration in MIGRATIONS {
+            apply(db.conn(), migration).await.unwrap();
+            snapshots.push(snapshot(&test_db).await);
+        }
+        for migration in MIGRATIONS.iter().rev() {
+            let reverted = db.rollback(Rollback::Steps(1)).await.unwrap();
+            assert_eq!(reverted[0].name, migration.name);
+            snapshots.pop();
+            assert_eq!(
+                snapshot(&test_db).await,
+                *snapshots.last().unwrap(),
+                "{} did not revert cleanly",
+                migration.name
+            );
+        }
+
+        assert_eq!(db.migrate().await.unwrap().len(), MIGRATIONS.len());
+        let version = MIGRATIONS[1].version();
+        let reverted = db.rollback(Rollback::To(version)).await.unwrap();
+        assert_eq!(reverted.len(), MIGRATIONS.len() - 2);
+        assert_eq!(db.redo().await.unwrap().name, MIGRATIONS[1].name);
+    }
+
+    #[tokio::test]
+    async fn dry_run_leaves_pending_migrations_pending() {
+        let test_db = TestDb::new().await;
+        let db = test_db.db();
+        db.rollback(Rollback::Steps(1)).await.unwrap();
+        let before = snapshot(&test_db).await;
+
+        let planned = db.dry_run().await.unwrap();
+        assert_eq!(planned.len(), 1);
+        assert_eq!(snapshot(&test_db).await, before);
+
+        let states: Vec<MigrationState> = db
+            .migration_status()
+            .await
+            .unwrap()
+            .iter()
+            .map(|status| status.state)
+            .collect();
+        assert_eq!(states.last(), Some(&MigrationState::Pending));
+        assert!(
+            states[..states.len() - 1]
+                .iter()
+                .all(|s| *s == MigrationState::Applied)
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_migrations_take_turns() {
+        let test_db = TestDb::new().await;
+        let db = test_db.db();
+        db.rollback(Rollback::Steps(MIGRATIONS.len()))
+            .await
+            .unwrap();
+
+        let (a, b) = tokio::join!(db.migrate(), db.migrate());
+        assert_eq!(a.unwrap().len() + b.unwrap().len(), MIGRATIONS.len());
+
+        let mut impatient = db.clone();
+        impatient.pool.migration_lock_timeout = Duration::from_millis(100);
+        let lock = db.lock_migrations().await.unwrap();
+        let err = impatient.migrate().await.unwrap_err().to_string();
+        assert!(
+            err.contains("waiting for the migration lock held by brize@"),
+            "{}",
+            err
+        );
+        lock.rollback().await.unwrap();
+        assert!(impatient.migrate().await.unwrap().is_empty());
+    }
+
+    // Everything runs on the lock's own connection, so one is enough
+    #[tokio::test]
+    async fn works_with_a_single_connection() {
+        let test_db = TestDb::new().await;
+        let pool = PoolSettings {
+            max_connections: 1,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(2),
+            migration_lock_timeout: Duration::from_millis(100),
+            ..test_db.db().pool.clone()
+        };
+        let db = Db::connect_as_owner(&test_db.url(), &pool).await.unwrap();
+
+        assert_eq!(db.rollback(Rollback::Steps(2)).await.unwrap().len(), 2);
+        assert_eq!(
+            db.redo().await.unwrap().name,
+            MIGRATIONS[MIGRATIONS.len() - 3].name
+        );
+        assert_eq!(db.migrate().await.unwrap().len(), 2);
+
+        // Adopting checks the adopted tables against schema.rs
+        db.rollback(Rollback::Steps(1)).await.unwrap();
+        db.conn()
+            .execute_unprepared(r#"DROP TABLE "schema_migrations""#)
+            .await
+            .unwrap();
+        assert_eq!(db.migrate().await.unwrap().len(), 1);
+
+        let lock = test_db.db().lock_migrations().await.unwrap();
+        let err = db.migrate().await.unwrap_err().to_string();
+        assert!(err.contains("held by brize@"), "{}", err);
+        lock.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_rollbacks_revert_nothing() {
+        let test_db = TestDb::new().await;
+        let db = test_db.db();
+        // Keeps activity_logs from being dropped, after the policies were
+        db.conn()
+            .execute_unprepared(r#"CREATE VIEW "logs" AS SELECT * FROM "activity_logs""#)
+            .await
+            .unwrap();
+        let before = snapshot(&test_db).await;
+
+        let err = db
+            .rollback(Rollback::Steps(2))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("Reverting 2025-08-05-190448_create_activity_log failed"),
+            "{}",
+            err
+        );
+        assert_eq!(snapshot(&test_db).await, before);
+        assert!(
+            db.migration_status()
+                .await
+                .unwrap()
+                .iter()
+                .all(|s| s.state == MigrationState::Applied)
+        );
+    }
+
+    #[tokio::test]
+    async fn adopts_databases_set_up_before_tracking() {
+        // Everything after the tables, which adoption never covers
+        let later = &MIGRATIONS[3..];
+        let later_names: Vec<&str> = later.iter().map(|m| m.name).collect();
+        // Tables made from SQL files directly, without any record of it
+        let test_db = TestDb::new().await;
+        let db = test_db.db();
+        db.rollback(Rollback::Steps(later.len())).await.unwrap();
+        db.conn()
+            .execute_unprepared(r#"DROP TABLE "schema_migrations""#)
+            .await
+            .unwrap();
+        let applied = db.migrate().await.unwrap();
+        assert_eq!(
+            applied.iter().map(|m| m.name).collect::<Vec<_>>(),
+            later_names
+        );
+
+        // Run by the diesel CLI
+        let test_db = TestDb::new().await;
+        let db = test_db.db();
+        db.rollback(Rollback::Steps(later.len())).await.unwrap();
+        db.conn()
+            .execute_unprepared(
+                r#"DROP TABLE "schema_migrations";
+                CREATE TABLE "__diesel_schema_migrations" ("version" varchar(50) PRIMARY KEY, "run_on" timestamp DEFAULT now() NOT NULL);
+                INSERT INTO "__diesel_schema_migrations" ("version") VALUES ('20250805190210'), ('20250805190435'), ('20250805190448')"#,
+            )
+            .await
+            .unwrap();
+        let applied = db.migrate().await.unwrap();
+        assert_eq!(
+            applied.iter().map(|m| m.name).collect::<Vec<_>>(),
+            later_names
+        );
+        assert!(
+            db.migration_status()
+                .await
+                .unwrap()
+                .iter()
+                .all(|s| s.state == MigrationState::Applied)
+        );
+    }
+
+    const DRIZZLE_0000: &str = include_str!("fixtures/drizzle/0000_initialize.sql");
+    const DRIZZLE_0001: &str = include_str!("fixtures/drizzle/0001_initialize_with_teams.sql");
+
+    #[test]
+    fn drizzle_histories_match_their_files() {
+        let history = &DRIZZLE_HISTORIES[0];
+        for ((_, hash), sql) in history.migrations.iter().zip([DRIZZLE_0000, DRIZZLE_0001]) {
+            assert_eq!(*hash, sha256(sql));
+        }
+        for history in DRIZZLE_HISTORIES {
+            let known: Vec<String> = MIGRATIONS[..history.versions.len()]
+                .iter()
+                .map(Migration::version)
+                .collect();
+            assert_eq!(known, history.versions);
+        }
+    }
+
+    // Laid out the way drizzle-kit generates the SaaS starter's schema: every table, then
+    // the foreign keys, split by statement-breakpoints
+    const SAAS_STARTER: &str = include_str!("fixtures/drizzle/saas_starter.sql");
+
+    fn sha256(sql: &str) -> String {
+        format!("{:x}", Sha256::digest(sql.as_bytes()))
+    }
+
+    // A database the way the Drizzle starter leaves it: tables in public, referenced as
+    // "public"."x", and drizzle-kit's history of `hashes` in a schema of its own
+    async fn drizzle_database(test_db: &TestDb, hashes: &[&str]) -> Db {
+        test_db
+            .db()
+            .rollback(Rollback::Steps(MIGRATIONS.len()))
+            .await
+            .unwrap();
+        let public = PoolSettings {
+            schema: "public".to_string(),
+            ..test_db.db().pool.clone()
+        };
+        let db = Db::connect_as_owner(&test_db.url(), &public).await.unwrap();
+        let history: Vec<String> = hashes
+            .iter()
+            .enumerate()
+            .map(|(i, hash)| format!("('{}', {})", hash, 1722885730000 + i as i64))
+            .collect();
+        db.conn()
+            .execute_unprepared(&format!(
+                r#"{}
+                CREATE SCHEMA "drizzle";
+                CREATE TABLE "drizzle"."__drizzle_migrations" ("id" serial PRIMARY KEY, "hash" text NOT NULL, "created_at" bigint);
+                INSERT INTO "drizzle"."__drizzle_migrations" ("hash", "created_at") VALUES {}"#,
+                SAAS_STARTER,
+                history.join(", ")
+            ))
+            .await
+            .unwrap();
+        db
+    }
+
+    fn states(statuses: &[MigrationStatus]) -> Vec<MigrationState> {
+        statuses.iter().map(|s| s.state).collect()
+    }
+
+    #[tokio::test]
+    async fn adopts_databases_from_the_drizzle_starter() {
+        let test_db = TestDb::new().await;
+        let db = drizzle_database(&test_db, &[&sha256(SAAS_STARTER)]).await;
+        // bcryptjs writes $2a$ hashes
+        let hash = bcrypt::hash_with_result("password", 4)
+            .unwrap()
+            .format_for_version(bcrypt::Version::TwoA);
+        users::ActiveModel {
+            email: Set("owner@test.com".to_string()),
+            password_hash: Set(hash.clone()),
+            role: Set("owner".to_string()),
+            ..Default::default()
+        }
+        .insert(db.conn())
+        .await
+        .unwrap();
+
+        // Its history isn't one brize knows, so the tables decide what is adopted
+        let applied = db.migrate().await.unwrap();
+        let later: Vec<&str> = MIGRATIONS[3..].iter().map(|m| m.name).collect();
+        assert_eq!(applied.iter().map(|m| m.name).collect::<Vec<_>>(), later);
+        let statuses = db.migration_status().await.unwrap();
+        assert!(
+            states(&statuses)
+                .iter()
+                .all(|s| *s == MigrationState::Applied)
+        );
+        assert!(db.check_schema().await.unwrap().is_empty());
+
+        let user = users::Entity::find()
+            .filter(users::Column::Email.eq("owner@test.com"))
+            .one(db.conn())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.password_hash, hash);
+        assert!(bcrypt::verify("password", &user.password_hash).unwrap());
+    }
+
+    #[tokio::test]
+    async fn adopts_known_drizzle_histories_by_their_versions() {
+        let test_db = TestDb::new().await;
+        let history = &DRIZZLE_HISTORIES[0];
+        let hashes: Vec<&str> = history.migrations.iter().map(|(_, hash)| *hash).collect();
+        let db = drizzle_database(&test_db, &hashes).await;
+
+        let applied = db.migrate().await.unwrap();
+        let later: Vec<&str> = MIGRATIONS[history.versions.len()..]
+            .iter()
+            .map(|m| m.name)
+            .collect();
+        assert_eq!(applied.iter().map(|m| m.name).collect::<Vec<_>>(), later);
+    }
+
+    #[tokio::test]
+    async fn refuses_drizzle_tables_missing_what_schema_rs_has() {
+        let test_db = TestDb::new().await;
+        let db = drizzle_database(&test_db, &[&sha256(SAAS_STARTER)]).await;
+        db.conn()
+            .execute_unprepared(r#"ALTER TABLE "users" DROP COLUMN "deleted_at""#)
+            .await
+            .unwrap();
+
+        let err = db.migrate().await.unwrap_err().to_string();
+        assert!(
+            err.contains("users.deleted_at: column is missing"),
+            "{}",
+            err
+        );
+        let statuses = db.migration_status().await.unwrap();
+        assert!(
+            states(&statuses)
+                .iter()
+                .all(|s| *s == MigrationState::Pending)
+        );
+
+        // Columns added by later Drizzle migrations are only warned about
+        let test_db = TestDb::new().await;
+        let db = drizzle_database(&test_db, &[&sha256(SAAS_STARTER)]).await;
+        db.conn()
+            .execute_unprepared(r#"ALTER TABLE "teams" ADD COLUMN "slug" text"#)
+            .await
+            .unwrap();
+        assert_eq!(db.migrate().await.unwrap().len(), MIGRATIONS.len() - 3);
+    }
+
+    #[tokio::test]
+    async fn refuses_to_run_after_an_applied_migration_changed() {
+        let test_db = TestDb::new().await;
+        assert!(test_db.db().migrate().await.unwrap().is_empty());
+
+        test_db
+            .conn()
+            .execute_unprepared(r#"UPDATE "schema_migrations" SET "checksum" = repeat('0', 64)"#)
+            .await
+            .unwrap();
+        let err = test_db.db().migrate().await.unwrap_err();
+        assert!(err.to_string().contains("was changed after it was applied"));
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use sea_orm::sqlx::postgres::{PgConnectOptions, PgSslMode};
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, DbErr, RuntimeErr,
+    Statement, sqlx,
+};
+use tokio::sync::OnceCell;
+
+use crate::tools::{AppError, PoolSettings, Result, Settings, SslMode, settings};
+
+mod migrate;
+mod replica;
+mod row_level_security;
+// The tables as the Diesel CLI prints them, for setup's diesel queries
+#[cfg(feature = "diesel")]
+pub mod schema;
+mod schema_check;
+#[cfg(any(test, feature = "test-support"))]
+mod test_db;
+mod transaction;
+pub use migrate::*;
+#[cfg(any(test, feature = "test-support"))]
+pub use test_db::*;
+pub use transaction::*;
+
+use replica::Replicas;
+
+/// Cloneable handle to the shared connection pool. Clones share the same pool.
+#[derive(Debug, Clone)]
+pub struct Db {
+    conn: DatabaseConnection,
+    replicas: Arc<Replicas>,
+    pool: PoolSettings,
+}
+
+/// Result of a readiness probe, see [`health`].
+#[derive(Debug, Clone)]
+pub struct Health {
+    /// Round trip of a `SELECT 1`
+    pub latency: Duration,
+    /// Open connections, idle or in use
+    pub connections: u32,
+    pub idle_connections: usize,
+    pub max_connections: u32,
+    /// Replicas currently serving reads, out of `replicas` configured
+    pub healthy_replicas: usize,
+    pub replicas: usize,
+}
+
+impl Db {
+    /// Opens a new pool tuned by `settings.pool`. Most code wants the shared [`db`] instead.
+    pub async fn connect(settings: &Settings) -> Result<Db> {
+        Db::connect_with(settings.postgres_url.expose(), &settings.pool)
+            .await?
+            .with_replicas(&settings.postgres_replica_urls, &settings.pool)
+            .await
+    }
+
+    pub async fn connect_with(url: &str, pool: &PoolSettings) -> Result<Db> {
+        open(url, pool).await?.check_role(pool).await
+    }
+
+    /// Like [`Db::connect_with`], for tools that run as the role owning the tables, such as
+    /// migrate, which `DB_ROW_LEVEL_SECURITY` would otherwise refuse.
+    pub async fn connect_as_owner(url: &str, pool: &PoolSettings) -> Result<Db> {
+        Ok(open(url, pool).await?)
+    }
+
+    /// Same as [`Db::writer`].
+    pub fn conn(&self) -> &DatabaseConnection {
+        &self.conn
+    }
+
+    /// The schema the app's tables live in, see [`PoolSettings::schema`].
+    pub fn schema(&self) -> &str {
+        &self.pool.schema
+    }
+
+    pub async fn create_schema(&self) -> Result<()> {
+        self.conn
+            .execute_unprepared(&format!(
+                "CREATE SCHEMA IF NOT EXISTS \"{}\"",
+                self.pool.schema
+            ))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn health(&self) -> Result<Health> {
+        let started = Instant::now();
+        self.conn
+            .execute(Statement::from_string(DbBackend::Postgres, "SELECT 1"))
+            .await?;
+        let latency = started.elapsed();
+
+        let pool = self.conn.get_postgres_connection_pool();
+        Ok(Health {
+            latency,
+            connections: pool.size(),
+            idle_connections: pool.num_idle(),
+            max_connections: pool.options().get_max_connections(),
+            healthy_replicas: self.replicas.healthy(),
+            replicas: self.replicas.len(),
+        })
+    }
+}
+
+async fn open(url: &str, pool: &PoolSettings) -> std::result::Result<Db, DbErr> {
+    Ok(Db {
+        conn: open_conn(url, pool, false).await?,
+        replicas: Arc::default(),
+        pool: pool.clone(),
+    })
+}
+
+// A lazy pool doesn't connect until first used, so it can't fail here
+async fn open_conn(
+    url: &str,
+    pool: &PoolSettings,
+    lazy: bool,
+) -> std::result::Result<DatabaseConnection, DbErr> {
+    let mut options = ConnectOptions::new(url);
+    options
+        .max_connections(pool.max_connections)
+        .min_connections(pool.min_connections)
+        .connect_timeout(pool.connect_timeout)
+        .idle_timeout(pool.idle_timeout)
+        .acquire_timeout(pool.acquire_timeout)
+        .connect_lazy(lazy)
+        .set_schema_search_path(pool.schema.as_str())
+        .map_sqlx_postgres_opts({
+            let pool = pool.clone();
+            move |opts| pg_options(opts, &pool)
+        });
+
+    Database::connect(options).await
+}
+
+// What sea-orm's ConnectOptions can't set. `opts` already holds what the URL asked for
+fn pg_options(opts: PgConnectOptions, pool: &PoolSettings) -> PgConnectOptions {
+    let mut opts = opts.application_name(&instance_name());
+    if let Some(mode) = pool.ssl_mode {
+        opts = opts.ssl_mode(pg_ssl_mode(mode));
+    }
+    if let Some(timeout) = pool.statement_timeout {
+        opts = opts.options([("statement_timeout", timeout.as_millis().to_string())]);
+    }
+    opts
+}
+
+/// Names this process in `pg_stat_activity`, e.g. to say who holds the migration lock.
+pub fn instance_name() -> String {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("brize@{}:{}", host, std::process::id())
+}
+
+/// Connects, retrying with exponential backoff until `pool.startup_timeout` runs out.
+/// Useful right after `docker compose up -d`, when Postgres takes a few seconds to accept connections.
+/// Connects like [`Db::connect_as_owner`], the app's own pool comes from [`init`].
+pub async fn connect_with_retry(url: &str, pool: &PoolSettings) -> Result<Db> {
+    let deadline = Instant::now() + pool.startup_timeout;
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        let err = match open_until(url, pool, deadline).await {
+            Ok(db) => return Ok(db),
+            Err(err) => err,
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if is_permanent(&err) || remaining.is_zero() {
+            tracing::warn!("Database not ready after {} attempt(s), giving up", attempt);
+            return Err(err.into());
+        }
+
+        let wait = delay.min(remaining);
+        tracing::warn!(
+            "Database not ready (attempt {}): {}, retrying in {:?}",
+            attempt,
+            err,
+            wait
+        );
+        tokio::time::sleep(wait).await;
+        delay = next_retry_delay(delay);
+        attempt += 1;
+    }
+}
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+fn next_retry_delay(delay: Duration) -> Duration {
+    (delay * 2).min(MAX_RETRY_DELAY)
+}
+
+// One attempt, cut short at the deadline rather than waiting out the whole
+// acquire_timeout. The pool keeps its acquire_timeout once connected. A zero
+// startup_timeout means a single attempt, so that one gets the full acquire_timeout
+async fn open_until(
+    url: &str,
+    pool: &PoolSettings,
+    deadline: Instant,
+) -> std::result::Result<Db, DbErr> {
+    let timeout = match pool.startup_timeout.is_zero() {
+        true => pool.acquire_timeout,
+        false => pool
+            .acquire_timeout
+            .min(deadline.saturating_duration_since(Instant::now())),
+    };
+    match tokio::time::timeout(timeout, open(url, pool)).await {
+        Ok(opened) => opened,
+        Err(_) => Err(DbErr::Conn(RuntimeErr::Internal(format!(
+            "no connection within {:?}",
+            timeout
+        )))),
+    }
+}
+
+// Bad credentials or a missing database won't fix themselves by waiting
+fn is_permanent(err: &DbErr) -> bool {
+    let (DbErr::Conn(RuntimeErr::SqlxError(sqlx::Error::Database(db_err)))
+    | DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Database(db_err)))
+    | DbErr::Query(RuntimeErr::SqlxError(sqlx::Error::Database(db_err)))) = err
+    else {
+        return false;
+    };
+    // 28xxx is invalid authorization, 3D000 an unknown database
+    db_err
+        .code()
+        .is_some_and(|code| code.starts_with("28") || code == "3D000")
+}
+
+static SHARED: OnceCell<Db> = OnceCell::const_new();
+
+/// Builds the shared pool from `settings`, or returns it if it already exists.
+pub async fn init(settings: &Settings) -> Result<Db> {
+    SHARED
+        .get_or_try_init(|| async {
+            connect_with_retry(settings.postgres_url.expose(), &settings.pool)
+                .await?
+                .check_role(&settings.pool)
+                .await?
+                .with_replicas(&settings.postgres_replica_urls, &settings.pool)
+                .await
+        })
+        .await
+        .cloned()
+}
+
+/// The shared pool, built from [`settings`] on first use.
+pub async fn db() -> Db {
+    match SHARED.get() {
+        Some(db) => db.clone(),
+        None => init(&settings())
+            .await
+            .expect("Failed to connect to database"),
+    }
+}
+
+/// Readiness probe for the shared pool. Fails instead of connecting when it hasn't been built yet.
+pub async fn health() -> Result<Health> {
+    match SHARED.get() {
+        Some(db) => db.health().await,
+        None => Err(AppError::Database(
+            "Database pool is not initialized".into(),
+        )),
+    }
+}
+
+fn pg_ssl_mode(mode: SslMode) -> PgSslMode {
+    match mode {
+        SslMode::Disable => PgSslMode::Disable,
+        SslMode::Allow => PgSslMode::Allow,
+        SslMode::Prefer => PgSslMode::Prefer,
+        SslMode::Require => PgSslMode::Require,
+        SslMode::VerifyCa => PgSslMode::VerifyCa,
+        SslMode::VerifyFull => PgSslMode::VerifyFull,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delays_double_up_to_five_seconds() {
+        let delays: Vec<u64> =
+            std::iter::successors(Some(FIRST_RETRY_DELAY), |d| Some(next_retry_delay(*d)))
+                .take(7)
+                .map(|d| d.as_millis() as u64)
+                .collect();
+        assert_eq!(delays, [250, 500, 1000, 2000, 4000, 5000, 5000]);
+    }
+
+    #[tokio::test]
+    async fn only_unknown_roles_and_databases_are_permanent() {
+        let test_db = TestDb::new().await;
+        let url = url::Url::parse(&test_db.url()).unwrap();
+        // sqlx keeps retrying a refused connection until the acquire_timeout
+        let pool = PoolSettings {
+            acquire_timeout: Duration::from_secs(1),
+            ..PoolSettings::default()
+        };
+        let attempt = |url: url::Url| {
+            let pool = pool.clone();
+            async move { open(url.as_str(), &pool).await.err().unwrap() }
+        };
+
+        // A role, as a wrong password passes where pg_hba.conf trusts local connections
+        let mut unknown_role = url.clone();
+        unknown_role.set_username("brize_no_such_role").unwrap();
+        assert!(is_permanent(&attempt(unknown_role).await));
+
+        let mut unknown_database = url.clone();
+        unknown_database.set_path("brize_no_such_database");
+        assert!(is_permanent(&attempt(unknown_database).await));
+
+        // Nothing listens on port 1, which may still change while waiting
+        let mut refused = url;
+        refused.set_port(Some(1)).unwrap();
+        assert!(!is_permanent(&attempt(refused).await));
+    }
+
+    #[tokio::test]
+    async fn retries_stop_at_the_startup_deadline() {
+        // 192.0.2.0/24 is reserved for documentation, so connecting hangs or fails
+        let pool = PoolSettings {
+            startup_timeout: Duration::from_secs(1),
+            acquire_timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(30),
+            ..PoolSettings::default()
+        };
+        let started = Instant::now();
+        assert!(
+            connect_with_retry("postgres://postgres@192.0.2.1/postgres", &pool)
+                .await
+                .is_err()
+        );
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn ssl_mode_overrides_the_url_only_when_set() {
+        let url: PgConnectOptions = "postgres://localhost/postgres?sslmode=require"
+            .parse()
+            .unwrap();
+        let mut pool = PoolSettings::default();
+        assert!(matches!(
+            pg_options(url.clone(), &pool).get_ssl_mode(),
+            PgSslMode::Require
+        ));
+
+        pool.ssl_mode = Some(SslMode::Disable);
+        assert!(matches!(
+            pg_options(url, &pool).get_ssl_mode(),
+            PgSslMode::Disable
+        ));
+    }
+}
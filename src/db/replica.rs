@@ -0,0 +1,236 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+
+use super::Db;
+use super::row_level_security::check_role_of;
+use crate::tools::{AppError, PoolSettings, Result, Secret};
+
+// One read-only replica. Starts out ejected until its first probe passes
+#[derive(Debug)]
+struct Replica {
+    conn: DatabaseConnection,
+    healthy: AtomicBool,
+    // Whether its role is known not to skip row-level security, or that doesn't matter
+    // because DB_ROW_LEVEL_SECURITY is off. Replicas unreachable at startup are checked
+    // by their first probe that gets through
+    role_checked: AtomicBool,
+}
+
+/// The replicas behind [`Db::reader`], shared by every clone of a [`Db`].
+#[derive(Debug, Default)]
+pub(super) struct Replicas {
+    list: Vec<Replica>,
+    next: AtomicUsize,
+}
+
+impl Replicas {
+    // Round robin over the healthy replicas, `None` when there are none
+    fn pick(&self) -> Option<&DatabaseConnection> {
+        let len = self.list.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..len)
+            .map(|i| &self.list[(start + i) % len])
+            .find(|replica| replica.healthy.load(Ordering::Relaxed))
+            .map(|replica| &replica.conn)
+    }
+
+    pub(super) fn healthy(&self) -> usize {
+        self.list
+            .iter()
+            .filter(|replica| replica.healthy.load(Ordering::Relaxed))
+            .count()
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    // All at once, so one that hangs doesn't hold up the others for its whole timeout
+    async fn check(&self, timeout: Duration) {
+        join_all(
+            self.list
+                .iter()
+                .enumerate()
+                .map(|(i, replica)| replica.check(i, timeout)),
+        )
+        .await;
+    }
+}
+
+impl Replica {
+    async fn check(&self, i: usize, timeout: Duration) {
+        let probe = async {
+            if !self.role_checked.load(Ordering::Relaxed) {
+                check_role_of(&self.conn).await?;
+                self.role_checked.store(true, Ordering::Relaxed);
+            }
+            self.conn
+                .execute(Statement::from_string(DbBackend::Postgres, "SELECT 1"))
+                .await?;
+            Ok::<_, AppError>(())
+        };
+        let healthy = match tokio::time::timeout(timeout, probe).await {
+            Ok(Ok(())) => true,
+            Ok(Err(AppError::Config(e))) => {
+                tracing::error!("Replica {}: {}", i, e);
+                false
+            }
+            _ => false,
+        };
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            match healthy {
+                true => tracing::info!("Replica {} is healthy, serving reads", i),
+                false => tracing::warn!("Replica {} failed its health check, ejected", i),
+            }
+        }
+    }
+}
+
+// Probes until every Db holding the replicas has been dropped
+async fn check_replicas(replicas: Weak<Replicas>, interval: Duration) {
+    while let Some(replicas) = replicas.upgrade() {
+        replicas.check(interval).await;
+        drop(replicas);
+        tokio::time::sleep(interval).await;
+    }
+}
+
+impl Db {
+    /// Adds read replicas to this pool and starts probing them every
+    /// `pool.replica_check_interval`. Unreachable replicas don't fail startup, they
+    /// just don't serve reads until they come up.
+    pub async fn with_replicas(
+        mut self,
+        urls: &[Secret<String>],
+        pool: &PoolSettings,
+    ) -> Result<Db> {
+        if urls.is_empty() {
+            return Ok(self);
+        }
+
+        let mut list = Vec::new();
+        for url in urls {
+            list.push(Replica {
+                conn: super::open_conn(url.expose(), pool, true).await?,
+                healthy: AtomicBool::new(false),
+                role_checked: AtomicBool::new(!pool.row_level_security),
+            });
+        }
+        // A replica that is up but connects as a bypass role fails startup like the
+        // primary. One that is down is given no longer than a probe, all of them at once
+        let checks = list
+            .iter()
+            .filter(|replica| !replica.role_checked.load(Ordering::Relaxed))
+            .map(|replica| async move {
+                let check = check_role_of(&replica.conn);
+                match tokio::time::timeout(pool.replica_check_interval, check).await {
+                    Ok(Ok(())) => {
+                        replica.role_checked.store(true, Ordering::Relaxed);
+                        Ok(())
+                    }
+                    Ok(Err(e @ AppError::Config(_))) => Err(e),
+                    _ => Ok(()),
+                }
+            });
+        join_all(checks).await.into_iter().collect::<Result<()>>()?;
+        let replicas = Arc::new(Replicas {
+            list,
+            next: AtomicUsize::new(0),
+        });
+
+        replicas.check(pool.replica_check_interval).await;
+        tokio::spawn(check_replicas(
+            Arc::downgrade(&replicas),
+            pool.replica_check_interval,
+        ));
+        self.replicas = replicas;
+        Ok(self)
+    }
+
+    /// Connection for reads that can tolerate replication lag, e.g. reporting pages.
+    /// Falls back to the primary when no replica is configured or healthy.
+    pub fn reader(&self) -> &DatabaseConnection {
+        self.replicas.pick().unwrap_or(&self.conn)
+    }
+
+    /// Connection to the primary, for writes and reads that must see them.
+    pub fn writer(&self) -> &DatabaseConnection {
+        &self.conn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_skip_ejected_replicas() {
+        let mut list = Vec::new();
+        for name in ["replica-1", "replica-2", "replica-3"] {
+            let url = format!("postgres://postgres@localhost/{}", name);
+            list.push(Replica {
+                conn: crate::db::open_conn(&url, &PoolSettings::default(), true)
+                    .await
+                    .unwrap(),
+                healthy: AtomicBool::new(true),
+                role_checked: AtomicBool::new(true),
+            });
+        }
+        let replicas = Replicas {
+            list,
+            next: AtomicUsize::new(0),
+        };
+        let picked = |replicas: &Replicas| {
+            let conn = replicas.pick()?;
+            replicas
+                .list
+                .iter()
+                .position(|r| std::ptr::eq(&r.conn, conn))
+        };
+
+        assert_eq!(picked(&replicas), Some(0));
+        assert_eq!(picked(&replicas), Some(1));
+        replicas.list[2].healthy.store(false, Ordering::Relaxed);
+        assert_eq!(picked(&replicas), Some(0));
+
+        for replica in &replicas.list {
+            replica.healthy.store(false, Ordering::Relaxed);
+        }
+        assert_eq!(picked(&replicas), None);
+    }
+
+    #[tokio::test]
+    async fn probes_replicas_at_once() {
+        let test_db = crate::db::TestDb::new().await;
+        let pool = PoolSettings {
+            row_level_security: true,
+            replica_check_interval: Duration::from_millis(500),
+            connect_timeout: Duration::from_secs(30),
+            acquire_timeout: Duration::from_secs(30),
+            ..PoolSettings::default()
+        };
+        // 192.0.2.0/24 is reserved for documentation, so connecting hangs or fails
+        let urls: Vec<Secret<String>> = (1..=4)
+            .map(|i| Secret::new(format!("postgres://postgres@192.0.2.{}/postgres", i)))
+            .collect();
+
+        // One by one, the startup role checks and first probes would take 4s
+        let started = std::time::Instant::now();
+        let db = test_db
+            .db()
+            .clone()
+            .with_replicas(&urls, &pool)
+            .await
+            .unwrap();
+        assert!(
+            started.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            started.elapsed()
+        );
+        assert_eq!(db.replicas.healthy(), 0);
+    }
+}
@@ -0,0 +1,255 @@
+use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+use super::{Db, TransactionOptions};
+use crate::tools::{AppError, PoolSettings, Result};
+
+impl Db {
+    /// Runs `f` in a transaction that can only see and write rows of `team_id` in the
+    /// team-scoped tables, once `DB_ROW_LEVEL_SECURITY` enabled the policies and unless
+    /// connected as a role that skips them. Handlers serving one team should do their work through
+    /// this, so a query missing its `team_id` filter can't leak another team's rows.
+    pub async fn team_transaction<T, F>(&self, team_id: i32, f: F) -> Result<T>
+    where
+        F: AsyncFnMut(&sea_orm::DatabaseTransaction) -> Result<T>,
+    {
+        self.transaction_with(TransactionOptions::team(team_id), f)
+            .await
+    }
+
+    /// [`Db::team_transaction`] for reads that can tolerate replication lag, run read-only on
+    /// [`Db::reader`]. Replicas are held to the same policies as the primary.
+    pub async fn team_read_transaction<T, F>(&self, team_id: i32, f: F) -> Result<T>
+    where
+        F: AsyncFnMut(&sea_orm::DatabaseTransaction) -> Result<T>,
+    {
+        let options = TransactionOptions {
+            read_only: true,
+            ..TransactionOptions::team(team_id)
+        };
+        self.transaction_with(options, f).await
+    }
+
+    pub(super) async fn check_role(self, pool: &PoolSettings) -> Result<Db> {
+        if pool.row_level_security {
+            check_role_of(&self.conn).await?;
+        }
+        Ok(self)
+    }
+}
+
+// The tables with a team isolation policy, see the team_row_level_security migration
+const TEAM_TABLES: [&str; 3] = ["activity_logs", "invitations", "team_members"];
+
+// The policies do nothing until row-level security is enabled on their tables, so
+// DB_ROW_LEVEL_SECURITY decides whether migrate turns it on. Only the owner can change it,
+// so the app's role can't turn it off again
+pub(super) async fn enable_row_level_security<C: ConnectionTrait>(
+    conn: &C,
+    enabled: bool,
+) -> Result<()> {
+    // A rollback may have removed the policies, and enabling it without them hides every row
+    let rows = conn
+        .query_all(Statement::from_string(
+            DbBackend::Postgres,
+            "SELECT tablename FROM pg_policies
+            WHERE schemaname = current_schema() AND policyname = tablename || '_team_isolation'",
+        ))
+        .await?;
+    let action = match enabled {
+        true => "ENABLE",
+        false => "DISABLE",
+    };
+    for row in rows {
+        let table: String = row.try_get("", "tablename")?;
+        conn.execute_unprepared(&format!(
+            r#"ALTER TABLE "{}" {} ROW LEVEL SECURITY"#,
+            table, action
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+// The owner of the team tables, superusers and BYPASSRLS roles skip every policy, and
+// none apply before migrate enabled them, which would make DB_ROW_LEVEL_SECURITY
+// silently do nothing
+pub(super) async fn check_role_of<C: ConnectionTrait>(conn: &C) -> Result<()> {
+    let tables = TEAM_TABLES
+        .iter()
+        .map(|table| format!("'{}'", table))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let row = conn
+        .query_one(Statement::from_string(
+            DbBackend::Postgres,
+            format!(
+                "SELECT current_user AS name, rolsuper OR rolbypassrls AS bypasses,
+                    EXISTS (
+                        SELECT 1 FROM pg_tables
+                        WHERE schemaname = current_schema() AND tablename IN ({tables})
+                            AND pg_has_role(current_user, tableowner, 'USAGE')
+                    ) AS owns,
+                    EXISTS (
+                        SELECT 1 FROM pg_tables
+                        WHERE schemaname = current_schema() AND tablename IN ({tables})
+                            AND NOT rowsecurity
+                    ) AS disabled
+                FROM pg_roles WHERE rolname = current_user",
+                tables = tables
+            ),
+        ))
+        .await?
+        .ok_or_else(|| AppError::Database("Current role not found in pg_roles".into()))?;
+    let name: String = row.try_get("", "name")?;
+    let bypasses: bool = row.try_get("", "bypasses")?;
+    let owns: bool = row.try_get("", "owns")?;
+    let disabled: bool = row.try_get("", "disabled")?;
+
+    let reason = match (bypasses, owns) {
+        (true, _) => format!("role {} is a SUPERUSER or BYPASSRLS role", name),
+        (false, true) => format!("role {} owns the team tables", name),
+        (false, false) if disabled => {
+            return Err(AppError::Config(
+                "DB_ROW_LEVEL_SECURITY is on, but row-level security isn't enabled on the team \
+                 tables; run migrate with DB_ROW_LEVEL_SECURITY=true as the tables' owner"
+                    .into(),
+            ));
+        }
+        (false, false) => return Ok(()),
+    };
+    Err(AppError::Config(
+        format!(
+            "DB_ROW_LEVEL_SECURITY is on, but {} and skips row-level security; \
+             connect as a role that was only granted access to the tables",
+            reason
+        )
+        .into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+    use crate::tools::Secret;
+
+    #[tokio::test]
+    async fn team_transactions_only_see_their_team() {
+        let test_db = TestDb::new().await;
+        let role = format!("{}_app", test_db.name());
+        test_db
+            .conn()
+            .execute_unprepared(&format!(
+                r#"INSERT INTO "users" ("id", "email", "password_hash") VALUES
+                    (1, 'a@example.com', 'x'), (2, 'b@example.com', 'x');
+                INSERT INTO "teams" ("id", "name") VALUES (1, 'A'), (2, 'B');
+                INSERT INTO "team_members" ("user_id", "team_id", "role") VALUES
+                    (1, 1, 'owner'), (2, 2, 'owner');
+                INSERT INTO "invitations" ("team_id", "email", "role", "invited_by") VALUES
+                    (1, 'c@example.com', 'member', 1), (2, 'd@example.com', 'member', 2);
+                INSERT INTO "activity_logs" ("team_id", "user_id", "action") VALUES
+                    (1, 1, 'SIGN_UP'), (2, 2, 'SIGN_UP');
+
+                DROP ROLE IF EXISTS "{role}";
+                CREATE ROLE "{role}" LOGIN PASSWORD 'password';
+                GRANT USAGE ON SCHEMA "{schema}" TO "{role}";
+                GRANT SELECT, INSERT ON ALL TABLES IN SCHEMA "{schema}" TO "{role}";
+                GRANT USAGE ON ALL SEQUENCES IN SCHEMA "{schema}" TO "{role}";"#,
+                role = role,
+                schema = test_db.db().schema(),
+            ))
+            .await
+            .unwrap();
+
+        let mut url = url::Url::parse(&test_db.url()).unwrap();
+        url.set_username(&role).unwrap();
+        url.set_password(Some("password")).unwrap();
+        let pool = PoolSettings {
+            row_level_security: true,
+            schema: test_db.db().schema().to_string(),
+            min_connections: 0,
+            ..PoolSettings::default()
+        };
+        // TestDb migrates with it off, which leaves the policies disabled
+        let err = Db::connect_with(url.as_str(), &pool).await.unwrap_err();
+        assert!(err.to_string().contains("isn't enabled"), "{}", err);
+
+        // migrate runs as the owner, which the app itself may not connect as
+        let owner = Db::connect_as_owner(&test_db.url(), &pool).await.unwrap();
+        owner.migrate().await.unwrap();
+        // The same database stands in for a replica
+        let app = Db::connect_with(url.as_str(), &pool)
+            .await
+            .unwrap()
+            .with_replicas(&[Secret::new(url.to_string())], &pool)
+            .await
+            .unwrap();
+        assert_eq!(app.health().await.unwrap().healthy_replicas, 1);
+
+        let team_ids = |table: &'static str| {
+            let sql = format!(r#"SELECT "team_id" FROM "{}" ORDER BY "team_id""#, table);
+            async move |txn: &sea_orm::DatabaseTransaction| {
+                let rows = txn
+                    .query_all(Statement::from_string(DbBackend::Postgres, sql.clone()))
+                    .await?;
+                rows.iter()
+                    .map(|row| Ok(row.try_get::<i32>("", "team_id")?))
+                    .collect::<Result<Vec<i32>>>()
+            }
+        };
+        for table in TEAM_TABLES {
+            let seen = app.team_transaction(1, team_ids(table)).await.unwrap();
+            assert_eq!(seen, [1], "{}", table);
+            let seen = app.team_read_transaction(2, team_ids(table)).await.unwrap();
+            assert_eq!(seen, [2], "{}", table);
+            // Outside a team transaction nothing is visible
+            let seen = app.transaction(team_ids(table)).await.unwrap();
+            assert_eq!(seen, Vec::<i32>::new(), "{}", table);
+        }
+
+        let err = app
+            .team_transaction(1, async |txn| {
+                txn.execute_unprepared(
+                    r#"INSERT INTO "team_members" ("user_id", "team_id", "role") VALUES (1, 2, 'member')"#,
+                )
+                .await?;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("row-level security"), "{}", err);
+        let err = app
+            .team_read_transaction(1, async |txn| {
+                txn.execute_unprepared(r#"DELETE FROM "team_members""#)
+                    .await?;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("read-only"), "{}", err);
+
+        // The owner and superusers are refused when it's on
+        let err = Db::connect_with(&test_db.url(), &pool).await.unwrap_err();
+        assert!(
+            err.to_string().contains("skips row-level security"),
+            "{}",
+            err
+        );
+
+        // Migrating with it off again lets every role see every row
+        test_db.db().migrate().await.unwrap();
+        let seen = app.transaction(team_ids("team_members")).await.unwrap();
+        assert_eq!(seen, [1, 2]);
+
+        app.conn().clone().close().await.unwrap();
+        owner.conn().clone().close().await.unwrap();
+        test_db
+            .conn()
+            .execute_unprepared(&format!(
+                r#"DROP OWNED BY "{role}"; DROP ROLE "{role}""#,
+                role = role
+            ))
+            .await
+            .unwrap();
+    }
+}
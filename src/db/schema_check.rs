@@ -0,0 +1,275 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DbBackend, EntityTrait, IdenStatic, Iterable, Statement,
+};
+
+use super::Db;
+use crate::entities;
+use crate::tools::{DieselSchema, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Column {
+    // Postgres' udt_name, which is Diesel's type name in lowercase, e.g. varchar or int4
+    data_type: String,
+    max_length: Option<usize>,
+    nullable: bool,
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.data_type)?;
+        if let Some(max_length) = self.max_length {
+            write!(f, "({})", max_length)?;
+        }
+        match self.nullable {
+            true => write!(f, " null"),
+            false => write!(f, " not null"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shape {
+    tables: BTreeSet<String>,
+    // (table, column)
+    columns: BTreeMap<(String, String), Column>,
+    unique: BTreeSet<(String, String)>,
+    // (table, column, referenced table)
+    foreign_keys: BTreeSet<(String, String, String)>,
+}
+
+impl Db {
+    /// Compares the live tables in the app schema with `src/db/schema.rs` and the
+    /// entities, returning one line per difference. Empty when they match.
+    pub async fn check_schema(&self) -> Result<Vec<String>> {
+        check_schema_on(&self.conn).await
+    }
+}
+
+// Same as `Db::check_schema`, on a given connection such as the migration lock's
+pub(super) async fn check_schema_on<C: ConnectionTrait>(conn: &C) -> Result<Vec<String>> {
+    Ok(diff(&expected(), &live_shape(conn).await?))
+}
+
+async fn live_shape<C: ConnectionTrait>(conn: &C) -> Result<Shape> {
+    let mut shape = Shape::default();
+
+    let columns = query(
+        conn,
+        "SELECT c.table_name, c.column_name, c.udt_name, c.character_maximum_length,
+                c.is_nullable = 'YES' AS nullable
+            FROM information_schema.columns c
+            JOIN information_schema.tables t
+                ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+            WHERE c.table_schema = current_schema() AND t.table_type = 'BASE TABLE'
+                AND c.table_name <> 'schema_migrations'",
+    )
+    .await?;
+    for row in columns {
+        let table: String = row.try_get("", "table_name")?;
+        shape.tables.insert(table.clone());
+        shape.columns.insert(
+            (table, row.try_get("", "column_name")?),
+            Column {
+                data_type: row.try_get("", "udt_name")?,
+                max_length: row
+                    .try_get::<Option<i32>>("", "character_maximum_length")?
+                    .map(|n| n as usize),
+                nullable: row.try_get("", "nullable")?,
+            },
+        );
+    }
+
+    let unique = query(
+        conn,
+        "SELECT kcu.table_name, kcu.column_name
+            FROM information_schema.table_constraints tc
+            JOIN information_schema.key_column_usage kcu
+                ON kcu.constraint_schema = tc.constraint_schema
+                AND kcu.constraint_name = tc.constraint_name
+            WHERE tc.table_schema = current_schema() AND tc.constraint_type = 'UNIQUE'",
+    )
+    .await?;
+    for row in unique {
+        shape.unique.insert((
+            row.try_get("", "table_name")?,
+            row.try_get("", "column_name")?,
+        ));
+    }
+
+    let foreign_keys = query(
+        conn,
+        "SELECT kcu.table_name, kcu.column_name, ccu.table_name AS foreign_table
+            FROM information_schema.table_constraints tc
+            JOIN information_schema.key_column_usage kcu
+                ON kcu.constraint_schema = tc.constraint_schema
+                AND kcu.constraint_name = tc.constraint_name
+            JOIN information_schema.constraint_column_usage ccu
+                ON ccu.constraint_schema = tc.constraint_schema
+                AND ccu.constraint_name = tc.constraint_name
+            WHERE tc.table_schema = current_schema() AND tc.constraint_type = 'FOREIGN KEY'",
+    )
+    .await?;
+    for row in foreign_keys {
+        shape.foreign_keys.insert((
+            row.try_get("", "table_name")?,
+            row.try_get("", "column_name")?,
+            row.try_get("", "foreign_table")?,
+        ));
+    }
+
+    Ok(shape)
+}
+
+async fn query<C: ConnectionTrait>(conn: &C, sql: &str) -> Result<Vec<sea_orm::QueryResult>> {
+    Ok(conn
+        .query_all(Statement::from_string(DbBackend::Postgres, sql))
+        .await?)
+}
+
+fn expected() -> Shape {
+    let schema = DieselSchema::get();
+    let mut shape = Shape {
+        tables: schema.tables.iter().cloned().collect(),
+        foreign_keys: schema.foreign_keys.iter().cloned().collect(),
+        ..Shape::default()
+    };
+    for column in &schema.columns {
+        shape.columns.insert(
+            (column.table.clone(), column.name.clone()),
+            Column {
+                data_type: column.data_type.clone(),
+                max_length: column.max_length,
+                nullable: column.nullable,
+            },
+        );
+    }
+
+    // Diesel doesn't record unique constraints, so those come from the entities
+    add_unique::<entities::users::Entity>(&mut shape);
+    add_unique::<entities::teams::Entity>(&mut shape);
+    add_unique::<entities::team_members::Entity>(&mut shape);
+    add_unique::<entities::invitations::Entity>(&mut shape);
+    add_unique::<entities::activity_logs::Entity>(&mut shape);
+    shape
+}
+
+fn add_unique<E: EntityTrait>(shape: &mut Shape) {
+    let table = E::default().table_name().to_string();
+    for column in E::Column::iter() {
+        if column.def().is_unique() {
+            shape
+                .unique
+                .insert((table.clone(), column.as_str().to_string()));
+        }
+    }
+}
+
+fn diff(expected: &Shape, live: &Shape) -> Vec<String> {
+    let mut drift = Vec::new();
+
+    for table in expected.tables.difference(&live.tables) {
+        drift.push(format!("{}: table is missing", table));
+    }
+    for table in live.tables.difference(&expected.tables) {
+        drift.push(format!("{}: table is not in schema.rs", table));
+    }
+
+    // Columns of a missing table would only repeat the line above
+    let both = |table: &String| expected.tables.contains(table) && live.tables.contains(table);
+    for ((table, name), column) in &expected.columns {
+        if !both(table) {
+            continue;
+        }
+        match live.columns.get(&(table.clone(), name.clone())) {
+            None => drift.push(format!("{}.{}: column is missing", table, name)),
+            Some(found) if found != column => drift.push(format!(
+                "{}.{}: expected {}, found {}",
+                table, name, column, found
+            )),
+            Some(_) => (),
+        }
+    }
+    for (table, name) in live.columns.keys() {
+        if both(table)
+            && !expected
+                .columns
+                .contains_key(&(table.clone(), name.clone()))
+        {
+            drift.push(format!("{}.{}: column is not in schema.rs", table, name));
+        }
+    }
+
+    for (table, column) in expected.unique.difference(&live.unique) {
+        if both(table) {
+            drift.push(format!(
+                "{}.{}: unique constraint is missing",
+                table, column
+            ));
+        }
+    }
+    for (table, column) in live.unique.difference(&expected.unique) {
+        if both(table) {
+            drift.push(format!(
+                "{}.{}: unique constraint is not in the entities",
+                table, column
+            ));
+        }
+    }
+
+    for (table, column, parent) in expected.foreign_keys.difference(&live.foreign_keys) {
+        if both(table) {
+            drift.push(format!(
+                "{}.{}: foreign key to {} is missing",
+                table, column, parent
+            ));
+        }
+    }
+    for (table, column, parent) in live.foreign_keys.difference(&expected.foreign_keys) {
+        if both(table) {
+            drift.push(format!(
+                "{}.{}: foreign key to {} is not in schema.rs",
+                table, column, parent
+            ));
+        }
+    }
+
+    drift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+
+    #[tokio::test]
+    async fn migrations_match_schema_rs() {
+        let test_db = TestDb::new().await;
+        assert_eq!(
+            test_db.db().check_schema().await.unwrap(),
+            Vec::<String>::new()
+        );
+
+        test_db
+            .conn()
+            .execute_unprepared(
+                r#"ALTER TABLE "users" ALTER COLUMN "name" TYPE varchar(50);
+                ALTER TABLE "users" DROP CONSTRAINT "users_email_unique";
+                ALTER TABLE "invitations" DROP CONSTRAINT "invitations_team_id_teams_id_fk";
+                ALTER TABLE "teams" ADD COLUMN "slug" text"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            test_db.db().check_schema().await.unwrap(),
+            [
+                "users.name: expected varchar(100) null, found varchar(50) null",
+                "teams.slug: column is not in schema.rs",
+                "users.email: unique constraint is missing",
+                "invitations.team_id: foreign key to teams is missing",
+            ]
+        );
+    }
+}
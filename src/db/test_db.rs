@@ -0,0 +1,191 @@
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use config::{Config, File};
+use sea_orm::{ConnectionTrait, DatabaseConnection};
+use url::{Host, Url};
+
+use super::Db;
+use crate::tools::{AppError, Detail, Profile, Result, Settings};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+// brize's own config/, so tests of other crates in the workspace find it too
+const ROOT: &str = env!("CARGO_MANIFEST_DIR");
+
+/// A freshly migrated database of its own, dropped again with the `TestDb`. Tests that
+/// each make one can run in parallel without seeing each other's rows.
+///
+/// Created on the server in `TEST_POSTGRES_URL`, or else `config/test.yaml`'s `postgres_url`,
+/// as a role allowed to `CREATE DATABASE`. An exported `POSTGRES_URL` is never used, and
+/// servers other than this machine are refused unless `TEST_POSTGRES_ALLOW_REMOTE=true`.
+pub struct TestDb {
+    db: Db,
+    name: String,
+    admin_url: String,
+}
+
+impl TestDb {
+    pub async fn new() -> TestDb {
+        TestDb::create()
+            .await
+            .unwrap_or_else(|e| panic!("Failed to create test database: {}", e))
+    }
+
+    async fn create() -> Result<TestDb> {
+        let settings = Settings::load_in(Path::new(ROOT), Profile::Test)?;
+        let admin_url = server_url()?;
+        let name = format!(
+            "brize_test_{}_{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let admin = sea_orm::Database::connect(&admin_url).await?;
+        // Left over from a run that was killed before it could clean up
+        admin
+            .execute_unprepared(&format!("DROP DATABASE IF EXISTS \"{}\"", name))
+            .await?;
+        admin
+            .execute_unprepared(&format!("CREATE DATABASE \"{}\"", name))
+            .await?;
+        admin.close().await?;
+
+        let mut url = Url::parse(&admin_url).expect("Checked by server_url");
+        url.set_path(&name);
+        let test_db = TestDb {
+            db: Db::connect_as_owner(url.as_str(), &settings.pool).await?,
+            name,
+            admin_url,
+        };
+        test_db.db.create_schema().await?;
+        test_db.db.migrate().await?;
+        Ok(test_db)
+    }
+
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    pub fn conn(&self) -> &DatabaseConnection {
+        self.db.conn()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The test database's own URL, e.g. to connect to it with other settings.
+    pub fn url(&self) -> String {
+        let mut url = Url::parse(&self.admin_url).expect("Checked when the database was created");
+        url.set_path(&self.name);
+        url.to_string()
+    }
+}
+
+// Tests create and drop databases, so they only go to a server picked for them
+fn server_url() -> Result<String> {
+    let (key, url) = match std::env::var("TEST_POSTGRES_URL") {
+        Ok(url) => ("TEST_POSTGRES_URL", url),
+        Err(_) => {
+            let config = Config::builder()
+                .add_source(File::from(Path::new(ROOT).join("config/test.yaml")))
+                .build()?;
+            (
+                "postgres_url in config/test.yaml",
+                config.get_string("postgres_url")?,
+            )
+        }
+    };
+
+    let allow_remote = std::env::var("TEST_POSTGRES_ALLOW_REMOTE").is_ok_and(|v| v == "true");
+    check_server(key, &url, allow_remote)?;
+    Ok(url)
+}
+
+fn check_server(key: &str, url: &str, allow_remote: bool) -> Result<()> {
+    let url = Url::parse(url).map_err(|e| {
+        AppError::Config(Detail::with_source(
+            format!("{} is not a valid URL", key),
+            e,
+        ))
+    })?;
+    // postgres:// isn't a scheme `url` knows, so IPv4 hosts come back as domains. No host
+    // means a Unix socket
+    let local = match url.host() {
+        Some(Host::Domain(host)) => {
+            host == "localhost" || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+        }
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => true,
+    };
+    if !local && !allow_remote {
+        return Err(AppError::Config(
+            format!(
+                "{} points at {}, not this machine; set TEST_POSTGRES_ALLOW_REMOTE=true \
+                 if tests may create databases there",
+                key,
+                url.host_str().unwrap_or_default()
+            )
+            .into(),
+        ));
+    }
+    Ok(())
+}
+
+// Drop can't await, so the database is dropped from a runtime of its own. FORCE closes
+// the connections our pool still holds
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let admin_url = self.admin_url.clone();
+        let sql = format!("DROP DATABASE IF EXISTS \"{}\" WITH (FORCE)", self.name);
+        let dropped = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build runtime");
+            runtime.block_on(async {
+                let admin = sea_orm::Database::connect(&admin_url).await?;
+                admin.execute_unprepared(&sql).await?;
+                admin.close().await
+            })
+        })
+        .join();
+
+        if !matches!(dropped, Ok(Ok(()))) {
+            tracing::warn!("Failed to drop test database {}", self.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_local_servers_unless_allowed() {
+        for url in [
+            "postgres://postgres@localhost:5432/postgres",
+            "postgres://postgres@127.0.0.1/postgres",
+            "postgres://postgres@[::1]/postgres",
+        ] {
+            assert!(
+                check_server("TEST_POSTGRES_URL", url, false).is_ok(),
+                "{}",
+                url
+            );
+        }
+
+        let remote = "postgres://postgres@db.example.com/postgres";
+        let err = check_server("TEST_POSTGRES_URL", remote, false).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("TEST_POSTGRES_URL points at db.example.com"),
+            "{}",
+            err
+        );
+        assert!(check_server("TEST_POSTGRES_URL", remote, true).is_ok());
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use sea_orm::{
+    AccessMode, ConnectionTrait, DatabaseTransaction, DbBackend, IsolationLevel, Statement,
+    TransactionTrait,
+};
+
+use super::{Db, db};
+use crate::tools::{AppError, Detail, Result};
+
+/// How [`Db::transaction_with`] runs its closure.
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionOptions {
+    /// `None` keeps the server default, which is read committed for Postgres.
+    pub isolation: Option<IsolationLevel>,
+    /// Extra attempts after a serialization failure (40001) or deadlock (40P01).
+    pub max_retries: u32,
+    /// Sets `app.current_team_id` for the transaction, which the row-level security
+    /// policies restrict team-scoped tables to.
+    pub team_id: Option<i32>,
+    /// Runs on [`Db::reader`] as a read-only transaction, so it may lag behind the primary.
+    pub read_only: bool,
+}
+
+impl Default for TransactionOptions {
+    fn default() -> Self {
+        TransactionOptions {
+            isolation: None,
+            max_retries: 3,
+            team_id: None,
+            read_only: false,
+        }
+    }
+}
+
+impl TransactionOptions {
+    pub fn isolation(level: IsolationLevel) -> Self {
+        TransactionOptions {
+            isolation: Some(level),
+            ..TransactionOptions::default()
+        }
+    }
+
+    pub fn team(team_id: i32) -> Self {
+        TransactionOptions {
+            team_id: Some(team_id),
+            ..TransactionOptions::default()
+        }
+    }
+}
+
+impl Db {
+    /// Runs `f` in a transaction with the default [`TransactionOptions`], e.g.
+    /// `db.transaction(async |txn| { ... })`.
+    pub async fn transaction<T, F>(&self, f: F) -> Result<T>
+    where
+        F: AsyncFnMut(&DatabaseTransaction) -> Result<T>,
+    {
+        self.transaction_with(TransactionOptions::default(), f)
+            .await
+    }
+
+    /// Runs `f` in a transaction, committing when it returns `Ok` and rolling back otherwise.
+    ///
+    /// `f` is run again from the start when Postgres aborts the transaction with a serialization
+    /// failure or deadlock, so it must not have side effects outside the database. Once
+    /// `options.max_retries` runs out the last failure is returned as [`AppError::Database`].
+    pub async fn transaction_with<T, F>(&self, options: TransactionOptions, mut f: F) -> Result<T>
+    where
+        F: AsyncFnMut(&DatabaseTransaction) -> Result<T>,
+    {
+        let mut attempt = 0;
+        loop {
+            let err = match self.attempt(options, &mut f).await {
+                Err(AppError::Retryable(detail)) => detail,
+                result => return result,
+            };
+
+            if attempt >= options.max_retries {
+                return Err(AppError::Database(Detail::with_source(
+                    format!("Transaction failed after {} attempt(s)", attempt + 1),
+                    AppError::Retryable(err),
+                )));
+            }
+            attempt += 1;
+            tokio::time::sleep(Duration::from_millis(10 * 2u64.pow(attempt))).await;
+        }
+    }
+
+    async fn attempt<T, F>(&self, options: TransactionOptions, f: &mut F) -> Result<T>
+    where
+        F: AsyncFnMut(&DatabaseTransaction) -> Result<T>,
+    {
+        let txn = match options.read_only {
+            true => {
+                self.reader()
+                    .begin_with_config(options.isolation, Some(AccessMode::ReadOnly))
+                    .await?
+            }
+            false => self.conn.begin_with_config(options.isolation, None).await?,
+        };
+        if let Some(team_id) = options.team_id {
+            // `true` scopes it to this transaction, so it never leaks to the pool's next user
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT set_config('app.current_team_id', $1, true)",
+                [team_id.to_string().into()],
+            ))
+            .await?;
+        }
+        match f(&txn).await {
+            Ok(value) => {
+                txn.commit().await?;
+                Ok(value)
+            }
+            // The connection is discarded when the rollback fails, and `e` says what went wrong
+            Err(e) => {
+                if let Err(rollback) = txn.rollback().await {
+                    tracing::warn!("Rollback failed after {}: {}", e, rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Runs `f` in a transaction on the shared pool, see [`Db::transaction_with`].
+pub async fn transaction<T, F>(f: F) -> Result<T>
+where
+    F: AsyncFnMut(&DatabaseTransaction) -> Result<T>,
+{
+    db().await.transaction(f).await
+}
+
+pub async fn transaction_with<T, F>(options: TransactionOptions, f: F) -> Result<T>
+where
+    F: AsyncFnMut(&DatabaseTransaction) -> Result<T>,
+{
+    db().await.transaction_with(options, f).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDb;
+
+    #[tokio::test]
+    async fn retries_serialization_failures_then_gives_up() {
+        let test_db = TestDb::new().await;
+        let mut attempts = 0;
+        let options = TransactionOptions {
+            max_retries: 2,
+            ..TransactionOptions::default()
+        };
+
+        let err = test_db
+            .db()
+            .transaction_with(options, async |txn| {
+                attempts += 1;
+                txn.execute_unprepared(
+                    "DO $$ BEGIN RAISE EXCEPTION 'conflict' USING ERRCODE = '40001'; END $$",
+                )
+                .await?;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(attempts, 3);
+        assert!(matches!(err, AppError::Database(_)), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn rolls_back_when_the_closure_fails() {
+        let test_db = TestDb::new().await;
+        let err = test_db
+            .db()
+            .transaction(async |txn| {
+                txn.execute_unprepared("CREATE TABLE scratch (id int)")
+                    .await?;
+                Err::<(), _>(AppError::Database("changed my mind".into()))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Database error: changed my mind");
+
+        let found = test_db
+            .db()
+            .transaction(async |txn| {
+                Ok(txn
+                    .query_one(Statement::from_string(
+                        DbBackend::Postgres,
+                        "SELECT to_regclass('scratch') IS NOT NULL AS found",
+                    ))
+                    .await?
+                    .map(|row| row.try_get::<bool>("", "found"))
+                    .transpose()?)
+            })
+            .await
+            .unwrap();
+        assert_eq!(found, Some(false));
+    }
+}
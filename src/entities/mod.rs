@@ -0,0 +1,9 @@
+//! SeaORM entities for the tables created by the migrations.
+
+pub mod prelude;
+
+pub mod activity_logs;
+pub mod invitations;
+pub mod team_members;
+pub mod teams;
+pub mod users;
@@ -0,0 +1,5 @@
+pub use super::activity_logs::Entity as ActivityLogs;
+pub use super::invitations::Entity as Invitations;
+pub use super::team_members::Entity as TeamMembers;
+pub use super::teams::Entity as Teams;
+pub use super::users::Entity as Users;
@@ -0,0 +1,63 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "teams")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(column_type = "String(StringLen::N(100))")]
+    pub name: String,
+    pub created_at: TimeDateTime,
+    pub updated_at: TimeDateTime,
+    #[sea_orm(column_type = "Text", nullable, unique)]
+    pub stripe_customer_id: Option<String>,
+    #[sea_orm(column_type = "Text", nullable, unique)]
+    pub stripe_subscription_id: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub stripe_product_id: Option<String>,
+    #[sea_orm(column_type = "String(StringLen::N(50))", nullable)]
+    pub plan_name: Option<String>,
+    #[sea_orm(column_type = "String(StringLen::N(20))", nullable)]
+    pub subscription_status: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::activity_logs::Entity")]
+    ActivityLogs,
+    #[sea_orm(has_many = "super::invitations::Entity")]
+    Invitations,
+    #[sea_orm(has_many = "super::team_members::Entity")]
+    TeamMembers,
+}
+
+impl Related<super::activity_logs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ActivityLogs.def()
+    }
+}
+
+impl Related<super::invitations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invitations.def()
+    }
+}
+
+impl Related<super::team_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamMembers.def()
+    }
+}
+
+// A team's users, through their memberships
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::team_members::Relation::Users.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::team_members::Relation::Teams.def().rev())
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,60 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "users")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(column_type = "String(StringLen::N(100))", nullable)]
+    pub name: Option<String>,
+    #[sea_orm(column_type = "String(StringLen::N(255))", unique)]
+    pub email: String,
+    #[sea_orm(column_type = "Text")]
+    pub password_hash: String,
+    #[sea_orm(column_type = "String(StringLen::N(20))")]
+    pub role: String,
+    pub created_at: TimeDateTime,
+    pub updated_at: TimeDateTime,
+    pub deleted_at: Option<TimeDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::activity_logs::Entity")]
+    ActivityLogs,
+    #[sea_orm(has_many = "super::invitations::Entity")]
+    Invitations,
+    #[sea_orm(has_many = "super::team_members::Entity")]
+    TeamMembers,
+}
+
+impl Related<super::activity_logs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ActivityLogs.def()
+    }
+}
+
+impl Related<super::invitations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Invitations.def()
+    }
+}
+
+impl Related<super::team_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamMembers.def()
+    }
+}
+
+// A user's teams, through their memberships
+impl Related<super::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::team_members::Relation::Teams.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::team_members::Relation::Users.def().rev())
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
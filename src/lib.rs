@@ -1,13 +1,46 @@
 pub mod db;
+pub mod entities;
 pub mod tools;
 
 #[cfg(test)]
 mod tests {
+    use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+
     use super::*;
 
     #[tokio::test]
     async fn test_setup() {
-        db::db().await;
-        assert!(false)
+        let test_db = db::TestDb::new().await;
+        let user = entities::users::ActiveModel {
+            email: Set("test@test.com".to_string()),
+            password_hash: Set("hash".to_string()),
+            ..Default::default()
+        }
+        .insert(test_db.conn())
+        .await
+        .unwrap();
+
+        let found = entities::users::Entity::find_by_id(user.id)
+            .one(test_db.conn())
+            .await
+            .unwrap();
+        assert_eq!(found.unwrap().role, "member");
+    }
+
+    // Each TestDb is its own database, so these can run in parallel
+    #[tokio::test]
+    async fn test_databases_are_isolated() {
+        let (a, b) = tokio::join!(db::TestDb::new(), db::TestDb::new());
+        assert_ne!(a.name(), b.name());
+        entities::teams::ActiveModel {
+            name: Set("Test Team".to_string()),
+            ..Default::default()
+        }
+        .insert(a.conn())
+        .await
+        .unwrap();
+
+        let teams = entities::teams::Entity::find().all(b.conn()).await.unwrap();
+        assert!(teams.is_empty());
     }
 }
@@ -0,0 +1,211 @@
+use std::error::Error;
+
+use sea_orm::DbErr;
+use sea_orm::sqlx;
+
+use super::{AppError, Detail, ValidationErrors};
+
+// Postgres SQLSTATEs that map onto a domain error, see
+// https://www.postgresql.org/docs/current/errcodes-appendix.html
+const UNIQUE_VIOLATION: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const NOT_NULL_VIOLATION: &str = "23502";
+const CHECK_VIOLATION: &str = "23514";
+const STRING_DATA_RIGHT_TRUNCATION: &str = "22001";
+const SERIALIZATION_FAILURE: &str = "40001";
+const DEADLOCK_DETECTED: &str = "40P01";
+
+// What Postgres told us about a failed statement
+struct Violation<'a> {
+    code: Option<&'a str>,
+    table: Option<&'a str>,
+    constraint: Option<&'a str>,
+    column: Option<&'a str>,
+    message: &'a str,
+}
+
+fn classify(violation: Violation, source: impl Error + Send + Sync + 'static) -> AppError {
+    let constraint = violation.constraint.unwrap_or("unknown constraint");
+    match violation.code {
+        Some(UNIQUE_VIOLATION) => AppError::Conflict(Detail::with_source(
+            format!(
+                "Duplicate value for {}",
+                unique_column(constraint, violation.table).unwrap_or(constraint)
+            ),
+            source,
+        )),
+        // Deleting a row that others still point at, e.g. a team with members
+        Some(FOREIGN_KEY_VIOLATION) if violation.message.starts_with("update or delete") => {
+            let errors =
+                ValidationErrors::from(format!("Record is still referenced by {}", constraint));
+            AppError::Validation(errors.with_source(source))
+        }
+        Some(FOREIGN_KEY_VIOLATION) => AppError::NotFound(Detail::with_source(
+            format!("Referenced record for {} does not exist", constraint),
+            source,
+        )),
+        Some(NOT_NULL_VIOLATION | CHECK_VIOLATION | STRING_DATA_RIGHT_TRUNCATION) => {
+            let errors = match (violation.code, violation.column) {
+                (Some(NOT_NULL_VIOLATION), Some(column)) => {
+                    let mut errors = ValidationErrors::new();
+                    errors.add(column, "is required");
+                    errors
+                }
+                _ => ValidationErrors::from(violation.message),
+            };
+            AppError::Validation(errors.with_source(source))
+        }
+        Some(SERIALIZATION_FAILURE | DEADLOCK_DETECTED) => {
+            AppError::Retryable(Detail::with_source(violation.message, source))
+        }
+        _ => AppError::Database(Detail::with_source(violation.message, source)),
+    }
+}
+
+// The column of a single-column unique constraint, from the `{table}_{column}_unique` names
+// drizzle-kit gives them or Postgres' own `{table}_{column}_key`
+fn unique_column<'a>(constraint: &'a str, table: Option<&str>) -> Option<&'a str> {
+    let column = constraint.strip_prefix(table?)?.strip_prefix('_')?;
+    column
+        .strip_suffix("_unique")
+        .or_else(|| column.strip_suffix("_key"))
+}
+
+// Convert from sea-orm's DbErr, which wraps sqlx::Error
+impl From<DbErr> for AppError {
+    fn from(err: DbErr) -> Self {
+        let db_err = match &err {
+            DbErr::RecordNotFound(msg) => return AppError::NotFound(Detail::new(msg.clone())),
+            DbErr::Conn(sea_orm::RuntimeErr::SqlxError(e))
+            | DbErr::Exec(sea_orm::RuntimeErr::SqlxError(e))
+            | DbErr::Query(sea_orm::RuntimeErr::SqlxError(e)) => match e {
+                sqlx::Error::Database(db_err) => db_err,
+                _ => return AppError::Database(Detail::with_source(err.to_string(), err)),
+            },
+            _ => return AppError::Database(Detail::with_source(err.to_string(), err)),
+        };
+
+        let code = db_err.code().map(|code| code.into_owned());
+        let constraint = db_err.constraint().map(str::to_string);
+        let pg = db_err.try_downcast_ref::<sqlx::postgres::PgDatabaseError>();
+        let table = pg.and_then(|pg| pg.table()).map(str::to_string);
+        let column = pg.and_then(|pg| pg.column()).map(str::to_string);
+        let message = db_err.message().to_string();
+        classify(
+            Violation {
+                code: code.as_deref(),
+                table: table.as_deref(),
+                constraint: constraint.as_deref(),
+                column: column.as_deref(),
+                message: &message,
+            },
+            err,
+        )
+    }
+}
+
+// Convert from diesel's error, used by the setup crate
+#[cfg(feature = "diesel")]
+impl From<diesel::result::Error> for AppError {
+    fn from(err: diesel::result::Error) -> Self {
+        use diesel::result::{DatabaseErrorKind, Error};
+
+        let (kind, info) = match &err {
+            Error::NotFound => {
+                return AppError::NotFound(Detail::with_source("Record not found", err));
+            }
+            Error::DatabaseError(kind, info) => (kind, info),
+            _ => return AppError::Database(Detail::with_source(err.to_string(), err)),
+        };
+
+        // Diesel only exposes the SQLSTATE through its own kinds
+        let code = match kind {
+            DatabaseErrorKind::UniqueViolation => Some(UNIQUE_VIOLATION),
+            DatabaseErrorKind::ForeignKeyViolation => Some(FOREIGN_KEY_VIOLATION),
+            DatabaseErrorKind::NotNullViolation => Some(NOT_NULL_VIOLATION),
+            DatabaseErrorKind::CheckViolation => Some(CHECK_VIOLATION),
+            DatabaseErrorKind::SerializationFailure => Some(SERIALIZATION_FAILURE),
+            _ => None,
+        };
+        let table = info.table_name().map(str::to_string);
+        let constraint = info.constraint_name().map(str::to_string);
+        let column = info.column_name().map(str::to_string);
+        let message = info.message().to_string();
+        classify(
+            Violation {
+                code,
+                table: table.as_deref(),
+                constraint: constraint.as_deref(),
+                column: column.as_deref(),
+                message: &message,
+            },
+            err,
+        )
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl From<diesel::ConnectionError> for AppError {
+    fn from(err: diesel::ConnectionError) -> Self {
+        AppError::Database(Detail::with_source(err.to_string(), err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify_code(code: &str, constraint: Option<&str>, message: &str) -> AppError {
+        let violation = Violation {
+            code: Some(code),
+            table: Some("users"),
+            constraint,
+            column: None,
+            message,
+        };
+        classify(violation, std::fmt::Error)
+    }
+
+    #[test]
+    fn unique_violations_conflict_on_the_column() {
+        let err = classify_code(
+            UNIQUE_VIOLATION,
+            Some("users_email_unique"),
+            "duplicate key value violates unique constraint",
+        );
+        assert!(matches!(err, AppError::Conflict(_)));
+        assert_eq!(err.to_string(), "Conflict: Duplicate value for email");
+    }
+
+    #[test]
+    fn foreign_key_violations_depend_on_the_direction() {
+        let err = classify_code(
+            FOREIGN_KEY_VIOLATION,
+            Some("team_members_team_id_teams_id_fk"),
+            "insert or update on table \"team_members\" violates foreign key constraint",
+        );
+        assert!(matches!(err, AppError::NotFound(_)));
+
+        let err = classify_code(
+            FOREIGN_KEY_VIOLATION,
+            Some("team_members_team_id_teams_id_fk"),
+            "update or delete on table \"teams\" violates foreign key constraint",
+        );
+        assert!(matches!(err, AppError::Validation(_)));
+    }
+
+    #[test]
+    fn serialization_failures_and_deadlocks_are_retryable() {
+        for code in [SERIALIZATION_FAILURE, DEADLOCK_DETECTED] {
+            let err = classify_code(code, None, "could not serialize access");
+            assert!(matches!(err, AppError::Retryable(_)), "{}", code);
+        }
+    }
+
+    #[test]
+    fn unknown_codes_stay_database_errors() {
+        let err = classify_code("42P01", None, "relation \"users\" does not exist");
+        assert!(matches!(err, AppError::Database(_)));
+        assert_eq!(err.status(), 500);
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::LazyLock;
+
+// The tables the code was generated against, as the Diesel CLI printed them
+const SCHEMA_RS: &str = include_str!("../db/schema.rs");
+
+static SCHEMA: LazyLock<DieselSchema> = LazyLock::new(|| parse(SCHEMA_RS));
+
+/// One column of a `diesel::table!` in `src/db/schema.rs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SchemaColumn {
+    pub table: String,
+    pub name: String,
+    // Diesel's type name in lowercase, which is Postgres' udt_name, e.g. varchar or int4
+    pub data_type: String,
+    pub max_length: Option<usize>,
+    pub nullable: bool,
+}
+
+/// `src/db/schema.rs`, parsed once. Diesel doesn't record unique constraints.
+#[derive(Debug, Default)]
+pub(crate) struct DieselSchema {
+    pub tables: Vec<String>,
+    pub columns: Vec<SchemaColumn>,
+    // (table, column, referenced table), from `diesel::joinable!`
+    pub foreign_keys: Vec<(String, String, String)>,
+}
+
+impl DieselSchema {
+    pub fn get() -> &'static DieselSchema {
+        &SCHEMA
+    }
+
+    #[cfg(test)]
+    pub fn column(&self, table: &str, name: &str) -> Option<&SchemaColumn> {
+        self.columns
+            .iter()
+            .find(|c| c.table == table && c.name == name)
+    }
+}
+
+fn parse(schema_rs: &str) -> DieselSchema {
+    let mut schema = DieselSchema::default();
+    let mut table = String::new();
+    let mut max_length = None;
+
+    for line in schema_rs.lines().map(str::trim) {
+        if let Some(joinable) = line.strip_prefix("diesel::joinable!(") {
+            // activity_logs -> teams (team_id));
+            let Some((child, rest)) = joinable.split_once(" -> ") else {
+                continue;
+            };
+            let Some((parent, column)) = rest.split_once(" (") else {
+                continue;
+            };
+            let column = column.trim_end_matches([')', ';']);
+            schema
+                .foreign_keys
+                .push((child.to_string(), column.to_string(), parent.to_string()));
+        } else if let Some(n) = line.strip_prefix("#[max_length = ") {
+            max_length = n.trim_end_matches(']').parse().ok();
+        } else if let Some((column, data_type)) = line.split_once(" -> ") {
+            let data_type = data_type.trim_end_matches(',');
+            let (data_type, nullable) = match data_type.strip_prefix("Nullable<") {
+                Some(inner) => (inner.trim_end_matches('>'), true),
+                None => (data_type, false),
+            };
+            schema.columns.push(SchemaColumn {
+                table: table.clone(),
+                name: column.to_string(),
+                data_type: data_type.to_lowercase(),
+                max_length: max_length.take(),
+                nullable,
+            });
+        } else if let Some((name, _)) = line.split_once(" (") {
+            table = name.to_string();
+            schema.tables.push(table.clone());
+        }
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tables_columns_and_joins() {
+        let schema = parse(
+            "diesel::table! {
+                teams (id) {
+                    id -> Int4,
+                    #[max_length = 100]
+                    name -> Varchar,
+                }
+            }
+
+            diesel::table! {
+                invitations (id) {
+                    team_id -> Int4,
+                    #[max_length = 20]
+                    status -> Nullable<Varchar>,
+                }
+            }
+
+            diesel::joinable!(invitations -> teams (team_id));",
+        );
+
+        assert_eq!(schema.tables, ["teams", "invitations"]);
+        assert_eq!(
+            schema.column("invitations", "status"),
+            Some(&SchemaColumn {
+                table: "invitations".to_string(),
+                name: "status".to_string(),
+                data_type: "varchar".to_string(),
+                max_length: Some(20),
+                nullable: true,
+            })
+        );
+        assert_eq!(schema.column("teams", "id").unwrap().max_length, None);
+        assert_eq!(
+            schema.column("teams", "name").unwrap().max_length,
+            Some(100)
+        );
+        assert_eq!(
+            schema.foreign_keys,
+            [(
+                "invitations".to_string(),
+                "team_id".to_string(),
+                "teams".to_string()
+            )]
+        );
+    }
+}
@@ -1,12 +1,154 @@
+use std::error::Error;
 use std::fmt;
 
+use serde::Serialize;
+
+use super::{FieldError, ValidationErrors};
+
+/// Content type to send alongside [`AppError::to_problem_json`].
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
 #[derive(Debug)]
 pub enum AppError {
-    Config(String),
-    Database(String),
-    Network(String),
-    Validation(String),
-    Internal(String),
+    Config(Detail),
+    Database(Detail),
+    Network(Detail),
+    Validation(ValidationErrors),
+    Internal(Detail),
+    NotFound(Detail),
+    Conflict(Detail),
+    // Safe to retry as is, e.g. a serialization failure or deadlock
+    Retryable(Detail),
+}
+
+/// The message of an [`AppError`] plus the error that caused it, if any.
+#[derive(Debug)]
+pub struct Detail {
+    pub message: String,
+    source: Option<Box<dyn Error + Send + Sync + 'static>>,
+}
+
+impl Detail {
+    pub fn new(message: impl Into<String>) -> Self {
+        Detail {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn with_source(
+        message: impl Into<String>,
+        source: impl Error + Send + Sync + 'static,
+    ) -> Self {
+        Detail {
+            message: message.into(),
+            source: Some(Box::new(source)),
+        }
+    }
+}
+
+impl From<String> for Detail {
+    fn from(message: String) -> Self {
+        Detail::new(message)
+    }
+}
+
+impl From<&str> for Detail {
+    fn from(message: &str) -> Self {
+        Detail::new(message)
+    }
+}
+
+impl fmt::Display for Detail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl AppError {
+    /// Stable, machine-readable code. Clients may match on these, so never change one.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Config(_) => "config_error",
+            AppError::Database(_) => "database_error",
+            AppError::Network(_) => "network_error",
+            AppError::Validation(_) => "validation_error",
+            AppError::Internal(_) => "internal_error",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Retryable(_) => "retryable_error",
+        }
+    }
+
+    /// HTTP status code the web layer should respond with.
+    pub fn status(&self) -> u16 {
+        match self {
+            AppError::Validation(_) => 422,
+            AppError::NotFound(_) => 404,
+            AppError::Conflict(_) => 409,
+            AppError::Network(_) => 502,
+            AppError::Retryable(_) => 503,
+            AppError::Config(_) | AppError::Database(_) | AppError::Internal(_) => 500,
+        }
+    }
+
+    /// `None` for [`AppError::Validation`], which carries [`ValidationErrors`] instead.
+    pub fn detail(&self) -> Option<&Detail> {
+        match self {
+            AppError::Config(detail)
+            | AppError::Database(detail)
+            | AppError::Network(detail)
+            | AppError::Internal(detail)
+            | AppError::NotFound(detail)
+            | AppError::Conflict(detail)
+            | AppError::Retryable(detail) => Some(detail),
+            AppError::Validation(_) => None,
+        }
+    }
+
+    /// Renders an RFC 7807 problem document. Messages of server-side errors are left out so
+    /// that internals (SQL, hostnames, config keys) never reach the client.
+    pub fn to_problem_json(&self) -> String {
+        let status = self.status();
+        let (detail, errors) = match self {
+            AppError::Validation(errors) => (Some(errors.to_string()), Some(errors.errors())),
+            _ => (self.detail().map(|d| d.message.clone()), None),
+        };
+        let problem = Problem {
+            kind: "about:blank",
+            title: status_title(status),
+            status,
+            detail: detail.filter(|_| status < 500),
+            code: self.code(),
+            errors,
+        };
+        serde_json::to_string(&problem).expect("Problem always serializes")
+    }
+}
+
+#[derive(Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    code: &'static str,
+    // Per-field errors for validation problems, an RFC 7807 extension member
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<&'a [FieldError]>,
+}
+
+fn status_title(status: u16) -> &'static str {
+    match status {
+        404 => "Not Found",
+        409 => "Conflict",
+        422 => "Unprocessable Content",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
 }
 
 impl fmt::Display for AppError {
@@ -17,38 +159,79 @@ impl fmt::Display for AppError {
             AppError::Network(msg) => write!(f, "Network error: {}", msg),
             AppError::Validation(msg) => write!(f, "Validation error: {}", msg),
             AppError::Internal(msg) => write!(f, "Internal error: {}", msg),
+            AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            AppError::Retryable(msg) => write!(f, "Retryable error: {}", msg),
         }
     }
 }
 
-impl std::error::Error for AppError {}
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppError::Validation(errors) => errors.source(),
+            _ => self
+                .detail()?
+                .source
+                .as_deref()
+                .map(|e| e as &(dyn Error + 'static)),
+        }
+    }
+}
 
 // Convert from config::ConfigError
 impl From<config::ConfigError> for AppError {
     fn from(err: config::ConfigError) -> Self {
-        AppError::Config(err.to_string())
+        AppError::Config(Detail::with_source(err.to_string(), err))
     }
 }
 
 // You can add more From implementations for other library errors
 impl From<std::env::VarError> for AppError {
     fn from(err: std::env::VarError) -> Self {
-        AppError::Config(format!("Environment variable error: {}", err))
+        AppError::Config(Detail::with_source(
+            format!("Environment variable error: {}", err),
+            err,
+        ))
     }
 }
 
-// Example for other common errors you might encounter
-// impl From<serde_json::Error> for AppError {
-//     fn from(err: serde_json::Error) -> Self {
-//         AppError::Internal(format!("JSON parsing error: {}", err))
-//     }
-// }
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Internal(Detail::with_source(
+            format!("JSON parsing error: {}", err),
+            err,
+        ))
+    }
+}
 
-// Convert from sqlx::Error, which is sea-orm's error
-// impl From<sqlx::Error> for AppError {
-//     fn from(err: sqlx::Error) -> Self {
-//         AppError::Database(err.to_string())
-//     }
-// }
+// Database errors (sea-orm and diesel) are converted in db_error.rs
 
 pub type Result<T> = std::result::Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_underlying_error() {
+        let err: AppError = std::env::VarError::NotPresent.into();
+        assert_eq!(err.code(), "config_error");
+        assert!(err.source().unwrap().is::<std::env::VarError>());
+    }
+
+    #[test]
+    fn problem_json_hides_server_side_details() {
+        let err = AppError::Database("relation \"users\" does not exist".into());
+        assert_eq!(
+            err.to_problem_json(),
+            r#"{"type":"about:blank","title":"Internal Server Error","status":500,"code":"database_error"}"#
+        );
+
+        let err = AppError::Validation("email is required".into());
+        assert_eq!(
+            err.to_problem_json(),
+            r#"{"type":"about:blank","title":"Unprocessable Content","status":422,"detail":"email is required","code":"validation_error","errors":[{"message":"email is required"}]}"#
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use super::settings::{KEYS, Layer, layers};
+use super::{Profile, Result, Secret, Settings};
+
+/// Where one setting's effective value came from, see [`Settings::explain`].
+#[derive(Debug)]
+pub struct Provenance {
+    pub key: String,
+    /// The effective value, with secrets redacted. `None` when no layer sets the key.
+    pub value: Option<String>,
+    /// Name of the layer that won, e.g. `config/base`, `.env`, `env` or `APP_ env`.
+    pub source: Option<String>,
+    /// Layers that also set the key but were overridden by `source`.
+    pub shadowed: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Explanation {
+    pub profile: Profile,
+    pub keys: Vec<Provenance>,
+}
+
+impl Settings {
+    /// Shows which layer each setting comes from for the profile named by `APP_ENV`.
+    pub fn explain() -> Result<Explanation> {
+        Settings::explain_for(Profile::current()?)
+    }
+
+    pub fn explain_for(profile: Profile) -> Result<Explanation> {
+        explain_layers(profile, layers(profile)?)
+    }
+}
+
+// Split out so tests can pass layers that don't depend on the real environment
+fn explain_layers(profile: Profile, layers: Vec<Layer>) -> Result<Explanation> {
+    let mut collected = Vec::new();
+    for layer in layers {
+        collected.push((layer.name, layer.source.collect()?));
+    }
+
+    let mut keys = Vec::new();
+    for &(key, secret) in KEYS {
+        let file_key = format!("{}_file", key);
+        let mut names = vec![(key.to_string(), secret)];
+        // The *_FILE form only holds a path, so it's shown as is
+        if secret {
+            names.push((file_key, false));
+        }
+
+        for (name, secret) in names {
+            let mut setters: Vec<(&String, String)> = collected
+                .iter()
+                .filter_map(|(layer, values)| {
+                    let value = values.get(&name)?.to_string();
+                    Some((layer, value))
+                })
+                .collect();
+
+            let winner = setters.pop();
+            if winner.is_none() && name != key {
+                continue;
+            }
+            keys.push(Provenance {
+                key: name.to_uppercase(),
+                value: winner.as_ref().map(|(_, value)| match secret {
+                    true => Secret::new(value.clone()).to_string(),
+                    false => value.clone(),
+                }),
+                source: winner.map(|(layer, _)| layer.clone()),
+                shadowed: setters
+                    .into_iter()
+                    .map(|(layer, _)| layer.clone())
+                    .collect(),
+            });
+        }
+    }
+
+    Ok(Explanation { profile, keys })
+}
+
+impl Explanation {
+    /// One warning per key that is set in more than one layer.
+    pub fn warnings(&self) -> Vec<String> {
+        self.keys
+            .iter()
+            .filter(|p| !p.shadowed.is_empty())
+            .map(|p| {
+                format!(
+                    "{} from {} is shadowed by {}",
+                    p.key,
+                    p.shadowed.join(", "),
+                    p.source.as_deref().unwrap_or("-")
+                )
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "profile: {}", self.profile)?;
+        let width = self.keys.iter().map(|p| p.key.len()).max().unwrap_or(0);
+        for p in &self.keys {
+            writeln!(
+                f,
+                "{:width$}  {:12}  {}",
+                p.key,
+                p.source.as_deref().unwrap_or("(unset)"),
+                p.value.as_deref().unwrap_or("-"),
+                width = width
+            )?;
+        }
+        for warning in self.warnings() {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use config::{Environment, File, FileFormat};
+
+    use super::*;
+
+    fn env(prefix: Option<&str>, vars: &[(&str, &str)]) -> Environment {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        match prefix {
+            Some(prefix) => Environment::with_prefix(prefix),
+            None => Environment::default(),
+        }
+        .source(Some(vars))
+    }
+
+    fn explanation() -> Explanation {
+        let layers = vec![
+            Layer::new(
+                "config/base",
+                File::from_str("base_url: http://from-yaml", FileFormat::Yaml),
+            ),
+            Layer::new(
+                "env",
+                env(
+                    None,
+                    &[("BASE_URL", "http://from-env"), ("AUTH_SECRET", "hunter2")],
+                ),
+            ),
+            Layer::new(
+                "APP_ env",
+                env(Some("APP"), &[("APP_BASE_URL", "http://from-app-env")]),
+            ),
+        ];
+        explain_layers(Profile::Test, layers).unwrap()
+    }
+
+    fn provenance<'a>(explanation: &'a Explanation, key: &str) -> &'a Provenance {
+        explanation.keys.iter().find(|p| p.key == key).unwrap()
+    }
+
+    #[test]
+    fn app_prefixed_env_wins_over_plain_env_and_files() {
+        let explanation = explanation();
+        let base_url = provenance(&explanation, "BASE_URL");
+        assert_eq!(base_url.value.as_deref(), Some("http://from-app-env"));
+        assert_eq!(base_url.source.as_deref(), Some("APP_ env"));
+        assert_eq!(base_url.shadowed, ["config/base", "env"]);
+        assert_eq!(
+            explanation.warnings(),
+            ["BASE_URL from config/base, env is shadowed by APP_ env"]
+        );
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let explanation = explanation();
+        let auth_secret = provenance(&explanation, "AUTH_SECRET");
+        assert_eq!(auth_secret.value.as_deref(), Some("[REDACTED]"));
+        assert_eq!(auth_secret.source.as_deref(), Some("env"));
+        assert!(!explanation.to_string().contains("hunter2"));
+    }
+}
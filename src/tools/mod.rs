@@ -1,4 +1,17 @@
+mod db_error;
+mod diesel_schema;
 mod error;
+mod explain;
+mod profile;
+mod reload;
+mod secret;
 mod settings;
+mod validation;
+pub(crate) use diesel_schema::*;
 pub use error::*;
+pub use explain::*;
+pub use profile::*;
+pub use reload::*;
+pub use secret::*;
 pub use settings::*;
+pub use validation::*;
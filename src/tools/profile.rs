@@ -0,0 +1,65 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{AppError, Result};
+
+/// Which environment the app runs in, picked with `APP_ENV`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Dev,
+    Test,
+    Staging,
+    Prod,
+}
+
+impl Profile {
+    /// Reads `APP_ENV`, defaulting to `dev`. Tests pick `test` explicitly, with `APP_ENV=test`
+    /// or [`Settings::load_for`](super::Settings::load_for), as `TestDb` does.
+    pub fn current() -> Result<Profile> {
+        match std::env::var("APP_ENV") {
+            Ok(name) => name.parse(),
+            Err(std::env::VarError::NotPresent) => Ok(Profile::Dev),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Profile::Dev => "dev",
+            Profile::Test => "test",
+            Profile::Staging => "staging",
+            Profile::Prod => "prod",
+        }
+    }
+
+    // Tests must not depend on whatever a developer keeps in their local .env
+    pub fn reads_dotenv(&self) -> bool {
+        *self != Profile::Test
+    }
+}
+
+impl FromStr for Profile {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Profile> {
+        match s.trim().to_lowercase().as_str() {
+            "dev" | "development" => Ok(Profile::Dev),
+            "test" => Ok(Profile::Test),
+            "staging" => Ok(Profile::Staging),
+            "prod" | "production" => Ok(Profile::Prod),
+            other => Err(AppError::Config(
+                format!(
+                    "unknown APP_ENV '{}', expected dev, test, staging or prod",
+                    other
+                )
+                .into(),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
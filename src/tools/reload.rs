@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::{AppError, Profile, Result, Settings};
+
+/// Live view of [`Settings`] that follows edits to `config/` and `.env` without a restart.
+///
+/// Each valid edit publishes a new snapshot to every subscriber. An edit that fails validation
+/// is published to [`SettingsHandle::rejections`] instead, and the last good snapshot stays
+/// active. Only the runtime values
+/// (`log_level`, `rate_limit_per_minute`, `features`) are meant to be read live; connections
+/// built from the rest at startup are not rebuilt.
+pub struct SettingsHandle {
+    reloader: Reloader,
+    task: JoinHandle<()>,
+}
+
+// Everything a reload needs, shared by the handle and its polling task
+#[derive(Clone)]
+struct Reloader {
+    profile: Profile,
+    dir: PathBuf,
+    tx: Arc<watch::Sender<Arc<Settings>>>,
+    rejected: Arc<watch::Sender<Option<Arc<AppError>>>>,
+}
+
+impl SettingsHandle {
+    /// Loads settings for the profile named by `APP_ENV` and checks for edits every two seconds.
+    pub fn watch() -> Result<SettingsHandle> {
+        SettingsHandle::watch_for(Profile::current()?, Duration::from_secs(2))
+    }
+
+    /// Must be called from within a tokio runtime.
+    pub fn watch_for(profile: Profile, interval: Duration) -> Result<SettingsHandle> {
+        SettingsHandle::watch_in(Path::new("."), profile, interval)
+    }
+
+    // Like watch_for, with config/ and .env looked up in `dir`
+    fn watch_in(dir: &Path, profile: Profile, interval: Duration) -> Result<SettingsHandle> {
+        // Taken before loading, so an edit made while loading is picked up on the first check
+        let seen = fingerprint(dir);
+        let (tx, _) = watch::channel(Arc::new(Settings::load_in(dir, profile)?));
+        let (rejected, _) = watch::channel(None);
+        let reloader = Reloader {
+            profile,
+            dir: dir.to_path_buf(),
+            tx: Arc::new(tx),
+            rejected: Arc::new(rejected),
+        };
+        let task = tokio::spawn(poll(reloader.clone(), seen, interval));
+
+        Ok(SettingsHandle { reloader, task })
+    }
+
+    pub fn current(&self) -> Arc<Settings> {
+        Arc::clone(&self.reloader.tx.borrow())
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Settings>> {
+        self.reloader.tx.subscribe()
+    }
+
+    /// Why the last edit was rejected, or `None` once a later one loads.
+    pub fn rejections(&self) -> watch::Receiver<Option<Arc<AppError>>> {
+        self.reloader.rejected.subscribe()
+    }
+
+    /// Reloads right away instead of waiting for the next check. A rejected edit is published
+    /// to [`SettingsHandle::rejections`] like one the check finds, and returned.
+    pub fn reload(&self) -> std::result::Result<(), Arc<AppError>> {
+        self.reloader.reload()
+    }
+}
+
+impl Drop for SettingsHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Reloader {
+    fn reload(&self) -> std::result::Result<(), Arc<AppError>> {
+        match Settings::load_in(&self.dir, self.profile) {
+            Ok(settings) => {
+                self.tx.send_replace(Arc::new(settings));
+                self.rejected
+                    .send_if_modified(|rejected| rejected.take().is_some());
+                Ok(())
+            }
+            Err(e) => {
+                let e = Arc::new(e);
+                self.rejected.send_replace(Some(Arc::clone(&e)));
+                Err(e)
+            }
+        }
+    }
+}
+
+async fn poll(
+    reloader: Reloader,
+    mut last: Vec<(PathBuf, Option<SystemTime>)>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let current = fingerprint(&reloader.dir);
+        if current == last {
+            continue;
+        }
+        last = current;
+        // Published to the rejections channel already
+        let _ = reloader.reload();
+    }
+}
+
+// Modification times of every file that feeds Settings; any difference means something was edited
+fn fingerprint(dir: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir.join("config"))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    paths.push(dir.join(".env"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "base_url: http://localhost:3000
+postgres_url: postgres://localhost/brize
+stripe_secret_key: sk_test
+stripe_webhook_secret: whsec
+auth_secret: secret
+";
+
+    // Waits for the next value on `rx`, failing the test instead of hanging
+    async fn next<T: Clone>(rx: &mut watch::Receiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.changed())
+            .await
+            .expect("Timed out waiting for a reload")
+            .unwrap();
+        rx.borrow_and_update().clone()
+    }
+
+    #[tokio::test]
+    async fn publishes_valid_edits_and_keeps_the_last_good_config() {
+        let dir = std::env::temp_dir().join(format!("brize_reload_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("config")).unwrap();
+        let file = dir.join("config/test.yaml");
+        let edit = |contents: String, age: u64| {
+            std::fs::write(&file, contents).unwrap();
+            // Distinct mtimes, even on filesystems with coarse timestamps
+            let modified = SystemTime::now() - Duration::from_secs(age);
+            std::fs::File::options()
+                .write(true)
+                .open(&file)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        edit(format!("{}rate_limit_per_minute: 60\n", VALID), 30);
+
+        let handle =
+            SettingsHandle::watch_in(&dir, Profile::Test, Duration::from_millis(10)).unwrap();
+        let mut settings = handle.subscribe();
+        let mut rejections = handle.rejections();
+        assert_eq!(handle.current().rate_limit_per_minute, 60);
+
+        edit(format!("{}rate_limit_per_minute: 120\n", VALID), 20);
+        assert_eq!(next(&mut settings).await.rate_limit_per_minute, 120);
+
+        edit(format!("{}rate_limit_per_minute: lots\n", VALID), 10);
+        let rejection = next(&mut rejections).await.unwrap();
+        assert!(rejection.to_string().contains("RATE_LIMIT_PER_MINUTE"));
+        assert_eq!(handle.current().rate_limit_per_minute, 120);
+        assert_eq!(settings.borrow().rate_limit_per_minute, 120);
+        assert!(!settings.has_changed().unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn manual_reloads_publish_rejections_too() {
+        let dir = std::env::temp_dir().join(format!("brize_reload_now_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("config")).unwrap();
+        let file = dir.join("config/test.yaml");
+        std::fs::write(&file, VALID).unwrap();
+
+        // Never checks on its own during the test
+        let handle =
+            SettingsHandle::watch_in(&dir, Profile::Test, Duration::from_secs(3600)).unwrap();
+        let rejections = handle.rejections();
+
+        std::fs::write(&file, format!("{}rate_limit_per_minute: lots\n", VALID)).unwrap();
+        let err = handle.reload().unwrap_err();
+        assert!(err.to_string().contains("RATE_LIMIT_PER_MINUTE"));
+        assert!(Arc::ptr_eq(&err, rejections.borrow().as_ref().unwrap()));
+
+        std::fs::write(&file, format!("{}rate_limit_per_minute: 90\n", VALID)).unwrap();
+        handle.reload().unwrap();
+        assert!(rejections.borrow().is_none());
+        assert_eq!(handle.current().rate_limit_per_minute, 90);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
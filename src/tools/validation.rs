@@ -0,0 +1,232 @@
+use std::error::Error;
+use std::fmt;
+
+use serde::Serialize;
+
+use super::{AppError, Result};
+
+/// Column limits, matching the `#[max_length]`s in `src/db/schema.rs`.
+pub mod limits {
+    pub const USER_NAME: usize = 100;
+    pub const USER_EMAIL: usize = 255;
+    pub const USER_ROLE: usize = 20;
+    pub const TEAM_NAME: usize = 100;
+    pub const TEAM_PLAN_NAME: usize = 50;
+    pub const TEAM_SUBSCRIPTION_STATUS: usize = 20;
+    pub const TEAM_MEMBER_ROLE: usize = 50;
+    pub const INVITATION_EMAIL: usize = 255;
+    pub const INVITATION_ROLE: usize = 50;
+    pub const INVITATION_STATUS: usize = 20;
+    pub const ACTIVITY_LOG_IP_ADDRESS: usize = 45;
+}
+
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// One problem with a submitted value. `field` is `None` for errors about the form as a whole.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+/// Every problem found in one submission, carried by [`AppError::Validation`].
+#[derive(Debug, Default)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+    source: Option<Box<dyn Error + Send + Sync + 'static>>,
+}
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        ValidationErrors::default()
+    }
+
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: Some(field.into()),
+            message: message.into(),
+        });
+    }
+
+    pub fn add_form(&mut self, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: None,
+            message: message.into(),
+        });
+    }
+
+    pub fn with_source(mut self, source: impl Error + Send + Sync + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Messages for one field, for showing next to its input.
+    pub fn field(&self, field: &str) -> impl Iterator<Item = &str> {
+        self.errors
+            .iter()
+            .filter(move |e| e.field.as_deref() == Some(field))
+            .map(|e| e.message.as_str())
+    }
+
+    pub(super) fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+
+    /// `Ok` when nothing was added, otherwise an [`AppError::Validation`] with every error.
+    pub fn into_result(self) -> Result<()> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(AppError::Validation(self)),
+        }
+    }
+
+    pub fn check_required(&mut self, field: &str, value: &str) -> bool {
+        if value.trim().is_empty() {
+            self.add(field, "is required");
+            return false;
+        }
+        true
+    }
+
+    // varchar(n) limits count characters, not bytes
+    pub fn check_max_length(&mut self, field: &str, value: &str, max: usize) -> bool {
+        if value.chars().count() > max {
+            self.add(field, format!("max {} chars", max));
+            return false;
+        }
+        true
+    }
+
+    pub fn check_email(&mut self, field: &str, value: &str, max: usize) -> bool {
+        if !self.check_required(field, value) || !self.check_max_length(field, value, max) {
+            return false;
+        }
+        let valid = match value.split_once('@') {
+            Some((local, domain)) => {
+                !local.is_empty() && domain.contains('.') && !value.contains(char::is_whitespace)
+            }
+            None => false,
+        };
+        if !valid {
+            self.add(field, "is not a valid email address");
+        }
+        valid
+    }
+}
+
+impl From<String> for ValidationErrors {
+    fn from(message: String) -> Self {
+        let mut errors = ValidationErrors::new();
+        errors.add_form(message);
+        errors
+    }
+}
+
+impl From<&str> for ValidationErrors {
+    fn from(message: &str) -> Self {
+        ValidationErrors::from(message.to_string())
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.errors.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match &error.field {
+                Some(field) => write!(f, "{}: {}", field, error.message)?,
+                None => write!(f, "{}", error.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_sign_up(email: &str, password: &str, name: Option<&str>) -> Result<()> {
+    let mut errors = ValidationErrors::new();
+    errors.check_email("email", email, limits::USER_EMAIL);
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        errors.add("password", format!("min {} chars", MIN_PASSWORD_LENGTH));
+    }
+    if let Some(name) = name {
+        errors.check_max_length("name", name, limits::USER_NAME);
+    }
+    errors.into_result()
+}
+
+pub fn validate_invitation(email: &str, role: &str) -> Result<()> {
+    let mut errors = ValidationErrors::new();
+    errors.check_email("email", email, limits::INVITATION_EMAIL);
+    if errors.check_required("role", role) {
+        errors.check_max_length("role", role, limits::INVITATION_ROLE);
+    }
+    errors.into_result()
+}
+
+pub fn validate_team_name(name: &str) -> Result<()> {
+    let mut errors = ValidationErrors::new();
+    if errors.check_required("name", name) {
+        errors.check_max_length("name", name, limits::TEAM_NAME);
+    }
+    errors.into_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::DieselSchema;
+
+    #[test]
+    fn collects_every_field_error() {
+        let err = validate_sign_up("not-an-email", "short", Some(&"x".repeat(101))).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Validation error: email: is not a valid email address, password: min 8 chars, name: max 100 chars"
+        );
+        assert_eq!(
+            err.to_problem_json(),
+            r#"{"type":"about:blank","title":"Unprocessable Content","status":422,"detail":"email: is not a valid email address, password: min 8 chars, name: max 100 chars","code":"validation_error","errors":[{"field":"email","message":"is not a valid email address"},{"field":"password","message":"min 8 chars"},{"field":"name","message":"max 100 chars"}]}"#
+        );
+    }
+
+    // A migration that changes a varchar length must change its limit too
+    #[test]
+    fn limits_match_schema_rs() {
+        let limits = [
+            ("users", "name", limits::USER_NAME),
+            ("users", "email", limits::USER_EMAIL),
+            ("users", "role", limits::USER_ROLE),
+            ("teams", "name", limits::TEAM_NAME),
+            ("teams", "plan_name", limits::TEAM_PLAN_NAME),
+            (
+                "teams",
+                "subscription_status",
+                limits::TEAM_SUBSCRIPTION_STATUS,
+            ),
+            ("team_members", "role", limits::TEAM_MEMBER_ROLE),
+            ("invitations", "email", limits::INVITATION_EMAIL),
+            ("invitations", "role", limits::INVITATION_ROLE),
+            ("invitations", "status", limits::INVITATION_STATUS),
+            (
+                "activity_logs",
+                "ip_address",
+                limits::ACTIVITY_LOG_IP_ADDRESS,
+            ),
+        ];
+        let schema = DieselSchema::get();
+        for (table, column, limit) in limits {
+            let found = schema.column(table, column).and_then(|c| c.max_length);
+            assert_eq!(found, Some(limit), "{}.{}", table, column);
+        }
+    }
+}